@@ -0,0 +1,46 @@
+// benchmark.rs
+// Run with 'cargo bench'.  Or only some of them, with 'cargo bench -- detect'
+
+use std::path::{Path, PathBuf};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use filedate::metadata::DatedPathBuf;
+use filedate::stamp::stamp_str;  // imports the lib.rs namespace, so we can call its functions.
+
+const FILENAME: &str = "some_file_name.txt";
+const STAMPED_FILENAME: &str = "some_file_name_2020-03-04T170054-0800.tar.gz";
+const STAMPED_PATH: &str = "/var/backups/some_file_name_2020-03-04T170054-0800.tar.gz";
+const UNSTAMPED_PATH: &str = "/var/backups/some_file_name_v20200304.notes.tar.gz.part";
+const INVALID_STAMPED_FILENAME: &str = "some_file_name_2020-13-45T170054-0800.tar.gz";
+
+
+fn stamping(c: &mut Criterion) {
+    let some_datetime = filedate::datetime_from_string("2020-03-04T170054-0800").ok();
+    c.bench_function("stamp_str", |b| b.iter(|| stamp_str(black_box(FILENAME), &some_datetime)));
+    c.bench_function("stamp_str_now", |b| b.iter(|| stamp_str(black_box(FILENAME), &None)));
+    c.bench_function("restamp_str", |b| b.iter(|| {
+        filedate::stamp::restamp_str(black_box(STAMPED_FILENAME), &some_datetime, &Default::default())
+    }));
+}
+
+fn detection(c: &mut Criterion) {
+    let path = Path::new(STAMPED_PATH);
+    c.bench_function("detect_is_path_file8601", |b| b.iter(|| filedate::is_path_file8601(black_box(path))));
+    c.bench_function("detect_not_stamped", |b| b.iter(|| filedate::is_path_file8601(black_box(Path::new(FILENAME)))));
+    c.bench_function("detect_not_stamped_path", |b| b.iter(|| filedate::is_path_file8601(black_box(Path::new(UNSTAMPED_PATH)))));
+    c.bench_function("detect_invalid_datetime", |b| {
+        b.iter(|| filedate::is_path_file8601(black_box(Path::new(INVALID_STAMPED_FILENAME))))
+    });
+    c.bench_function("detect_find_stamp", |b| b.iter(|| filedate::find_stamp(black_box(STAMPED_FILENAME))));
+}
+
+fn extraction(c: &mut Criterion) {
+    c.bench_function("extract_split_stamped_name", |b| b.iter(|| filedate::split_stamped_name(black_box(STAMPED_FILENAME))));
+    c.bench_function("extract_metadata", |b| b.iter(|| {
+        DatedPathBuf::new_from_pathbuf(PathBuf::from(black_box(STAMPED_PATH))).map(|dated_path| dated_path.metadata())
+    }));
+}
+
+criterion_group!(benches, stamping, detection, extraction);
+criterion_main!(benches);
@@ -1,33 +1,720 @@
 // bin.rs
 // Standard Library
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 // Third Party
+use chrono::prelude::*;
+use chrono_tz::Tz;
 extern crate clap;
-use clap::{App, AppSettings, Arg, SubCommand};  //, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
 // Internals
 // pub mod stamp;
+use filedate::Error;
+use filedate::retention::{self, RetentionPlan, RetentionPolicy};
+use filedate::dates::StampFormat;
+use filedate::metadata::{DatedPathBuf, FileNameMeta};
+use filedate::output::{OutputFormat, Record};
+use filedate::stamp::{DateTimeSource, StampOptions, StampPlacement, StampSeparator, StampZone, Touched};
+use filedate::verify::{Verdict, Verification};
+use filedate::walk::{Walk, WalkOptions};
+
+const EXIT_DRIFT: i32 = 10;  // 'verify' found a file whose modification time is far from its stamp.
+const STAMP_FORMATS: [&str; 7] = ["hybrid", "basic", "extended", "minutes", "date", "millis", "micros"];
 
 // filedate::filename_to_json(&args[1]);
 
 // Private function for the CLI, which receives dates as Strings.
-fn cli_add_suffix(filename_str: &str, date_str: &str) {
-    match  filedate::datetime_from_string(date_str) {
-        None => {
-            println!("Invalid datetime string.");
-            std::process::exit(0);
+fn cli_stamp(stamp_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.
+    let options: StampOptions = stamp_options(stamp_matches);
+    if options.placement == StampPlacement::Front && options.separator == StampSeparator::Period {
+        // The period would be read back as the start of the extensions.
+        eprintln!("error: --separator '.' cannot be used with --placement front");
+        return 1;
+    }
+    let source: DateTimeSource = match datetime_source(stamp_matches) {
+        Ok(source) => source,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return 1;
+        }
+    };
+    let paths: Vec<PathBuf> = match input_paths(stamp_matches) {
+        Ok(paths) => paths,
+        Err(err) => return report_error(&err, 0)
+    };
+    let date_time: Option<DateTime<FixedOffset>> = match shared_datetime(stamp_matches, source, &options) {
+        Ok(date_time) => date_time,
+        Err(err) => return report_error(&err, 0)
+    };
+
+    let mut exit_code = 0;
+    for path in &paths {
+        let result = file_datetime(path, &date_time, source)
+            .and_then(|file_date_time| filedate::stamp::stamp_path_with(path, &file_date_time, &options))
+            .and_then(|new_path| rename_or_print(path, &new_path, stamp_matches));
+        if let Err(err) = result {
+            exit_code = report_error(&err, exit_code);
+        }
+    }
+    exit_code
+}
+
+fn cli_restamp(restamp_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.
+    let options: StampOptions = stamp_options(restamp_matches);
+    let source: DateTimeSource = match datetime_source(restamp_matches) {
+        Ok(source) => source,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return 1;
+        }
+    };
+    let keep_datetime: bool = restamp_matches.is_present("keep-datetime");
+    let paths: Vec<PathBuf> = match input_paths(restamp_matches) {
+        Ok(paths) => paths,
+        Err(err) => return report_error(&err, 0)
+    };
+    let date_time: Option<DateTime<FixedOffset>> = match shared_datetime(restamp_matches, source, &options) {
+        Ok(date_time) => date_time,
+        Err(err) => return report_error(&err, 0)
+    };
+
+    let mut exit_code = 0;
+    for path in &paths {
+        let new_path: Result<PathBuf, Error> = if keep_datetime {
+            filedate::stamp::convert_stamp_path(path, &options)
         }
-        Some(f) => {
-            let new_filename: String = filedate::stamp::stamp_str(filename_str, &Some(f));
-            println!("{}", new_filename);  //output the result
-        }                
+        else {
+            file_datetime(path, &date_time, source)
+                .and_then(|file_date_time| filedate::stamp::restamp_path(path, &file_date_time, &options))
+        };
+
+        if let Err(err) = new_path.and_then(|new_path| rename_or_print(path, &new_path, restamp_matches)) {
+            exit_code = report_error(&err, exit_code);
+        }
+    }
+    exit_code
+}
+
+fn cli_unstamp(unstamp_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.
+    let paths: Vec<PathBuf> = match input_paths(unstamp_matches) {
+        Ok(paths) => paths,
+        Err(err) => return report_error(&err, 0)
+    };
+
+    let mut exit_code = 0;
+    for path in &paths {
+        let result = filedate::stamp::unstamp_path(path)
+            .and_then(|new_path| rename_or_print(path, &new_path, unstamp_matches));
+        if let Err(err) = result {
+            exit_code = report_error(&err, exit_code);
+        }
+    }
+    exit_code
+}
+
+fn cli_touch(touch_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.
+    let set_atime: bool = touch_matches.is_present("atime");
+    let dry_run: bool = touch_matches.is_present("dry-run");
+    let mut exit_code = 0;
+    let files: Vec<PathBuf> = stamped_files(touch_matches, &mut exit_code);
+
+    let mut touches: Vec<Touched> = Vec::with_capacity(files.len());
+    for file in &files {
+        match filedate::stamp::touch_from_stamp(file, set_atime, dry_run) {
+            Ok(touched) => touches.push(touched),
+            Err(err) => exit_code = report_error(&err, exit_code)
+        }
+    }
+    if let Err(err) = print_records(output_format(touch_matches, OutputFormat::Table), &touches) {
+        exit_code = report_error(&err, exit_code);
+    }
+    if dry_run {
+        eprintln!("Dry run; nothing was changed.");
+    }
+    exit_code
+}
+
+fn cli_verify(verify_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.  Even when every file could be read, drift is a failure.
+    let tolerance: chrono::Duration = validated(verify_matches, "tolerance", filedate::dates::duration_from_string)
+        .unwrap_or_else(|| chrono::Duration::seconds(1));
+    let mut exit_code = 0;
+    let files: Vec<PathBuf> = stamped_files(verify_matches, &mut exit_code);
+
+    let mut verifications: Vec<Verification> = Vec::with_capacity(files.len());
+    for result in filedate::verify::verify_paths(&files, tolerance) {
+        match result {
+            Ok(verification) => verifications.push(verification),
+            Err(err) => exit_code = report_error(&err, exit_code)
+        }
+    }
+    let drift_count: usize = verifications.iter().filter(|each| each.verdict == Verdict::Drift).count();
+
+    if let Err(err) = print_records(output_format(verify_matches, OutputFormat::Table), &verifications) {
+        exit_code = report_error(&err, exit_code);
+    }
+    if exit_code == 0 && drift_count > 0 {
+        exit_code = EXIT_DRIFT;
+    }
+    exit_code
+}
+
+fn stamped_files(matches: &ArgMatches, exit_code: &mut i32) -> Vec<PathBuf> {
+    // The filename arguments, plus the stamped files in any directories among them.  Failures are reported.
+    // Files named directly are kept, stamped or not; so that a missing datetime is reported, not ignored.
+    let paths: Vec<PathBuf> = match input_paths(matches) {
+        Ok(paths) => paths,
+        Err(err) => {
+            *exit_code = report_error(&err, *exit_code);
+            return Vec::new();
+        }
+    };
+
+    set_jobs(matches);
+    let options: WalkOptions = walk_options(matches);
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        match filedate::walk::walk_directory(&path, &options) {
+            Ok(walk) => {
+                report_warnings(&walk.warnings);
+                files.extend(walk.files.into_iter().filter(|file| filedate::stamp::stamped_datetime(file).is_ok()));
+            },
+            Err(err) => *exit_code = report_error(&err, *exit_code)
+        }
+    }
+    files
+}
+
+fn cli_extract(extract_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.
+    let path = Path::new(extract_matches.value_of_os("path").unwrap());
+    let format: OutputFormat = output_format(extract_matches, OutputFormat::Json);
+    if extract_matches.is_present("check") && !path.exists() {
+        return report_error(&Error::NotFound(path.to_path_buf()), 0);
+    }
+    if !path.is_dir() {
+        let result = filedate::metadata_from_name_in_zone(path, named_zone(extract_matches))
+            .and_then(|metadata| match format {
+                // A single file is 1 JSON object, rather than an array.
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string(&metadata)?);
+                    Ok(())
+                },
+                _ => print_records(format, &[metadata])
+            });
+        return match result {
+            Ok(()) => 0,
+            Err(err) => report_error(&err, 0)
+        };
+    }
+
+    // Directories are walked; how deeply depends on --recursive and --max-depth.
+    set_jobs(extract_matches);
+    let walk: Walk = match filedate::walk::walk_directory(path, &walk_options(extract_matches)) {
+        Ok(walk) => walk,
+        Err(err) => return report_error(&err, 0)
+    };
+    let (mut data, warnings): (Vec<FileNameMeta>, Vec<Error>) = filedate::metadata::metadata_from_walk(walk);
+    report_warnings(&warnings);
+    if let Some(zone) = named_zone(extract_matches) {
+        data.iter_mut().for_each(|each| each.set_zone(zone));
+    }
+    if extract_matches.value_of("sort") == Some("datetime") {
+        // Oldest first.  Identical datetimes keep their path order.
+        data.sort_by_key(|each| each.utc_datetime);
+    }
+    match print_records(format, &data) {
+        Ok(()) => 0,
+        Err(err) => report_error(&err, 0)
+    }
+}
+
+fn print_records<R: Record>(format: OutputFormat, records: &[R]) -> Result<(), Error> {
+    // Writes to stdout, in 1 pass.
+    let mut stdout = std::io::stdout().lock();
+    filedate::output::write_records(&mut stdout, format, records)
+}
+
+fn output_format(matches: &ArgMatches, default: OutputFormat) -> OutputFormat {
+    if matches.is_present("json") {
+        return OutputFormat::Json;
+    }
+    validated(matches, "format", OutputFormat::from_name).unwrap_or(default)
+}
+
+fn validated<T>(matches: &ArgMatches, name: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+    // The value of an argument, already accepted by its Clap validator (or possible_values).  None when absent.
+    // A value that was accepted, but cannot be parsed, means the validator and parser disagree; that is a bug here,
+    // so it panics rather than quietly falling back to a default.
+    matches.value_of(name).map(|value| expect_valid(name, value, parse(value)))
+}
+
+fn expect_valid<T>(name: &str, value: &str, parsed: Option<T>) -> T {
+    // See validated().
+    parsed.unwrap_or_else(|| panic!("--{} '{}' passed validation, but could not be parsed", name, value))
+}
+
+fn report_error(error: &Error, exit_code: i32) -> i32 {
+    // Prints an error, and returns the new exit code.  When several files fail, the first error decides.
+    eprintln!("error: {}", error);
+    if exit_code != 0 {
+        return exit_code;
+    }
+    exit_code_of(error)
+}
+
+fn report_warnings(warnings: &[Error]) {
+    // Entries that were skipped while walking a directory.  They do not change the exit code.
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+fn exit_code_of(error: &Error) -> i32 {
+    // Each kind of error has its own exit code, so that scripts can tell them apart.
+    // Exit code 1 remains for invalid arguments, and other general failures.
+    match error {
+        Error::NoStamp(_) => 2,
+        Error::InvalidDateTime(_) => 3,
+        Error::NotFound(_) => 4,
+        Error::AlreadyExists(_) => 5,
+        Error::InvalidFileName(_) => 6,
+        Error::NotUtf8(_) => 7,
+        Error::SymlinkLoop(_) | Error::Io(..) => 8,
+        Error::Json(_) => 9
+    }
+}
+
+fn datetime_source(matches: &ArgMatches) -> Result<DateTimeSource, String> {
+    // --like, without --from, uses the other file's mtime.  The current datetime is not something another file has.
+    match validated(matches, "from", DateTimeSource::from_name) {
+        Some(DateTimeSource::Now) if matches.is_present("like") => Err("--like cannot be used with --from now".to_owned()),
+        Some(source) => Ok(source),
+        None if matches.is_present("like") => Ok(DateTimeSource::Modified),
+        None => Ok(DateTimeSource::Now)
+    }
+}
+
+fn shared_datetime(matches: &ArgMatches, source: DateTimeSource, options: &StampOptions)
+    -> Result<Option<DateTime<FixedOffset>>, Error> {
+    // The instant shared by every file in a run.  Even if the clock ticks while renaming.
+    // None when each file's own metadata decides instead.
+    let date_time: Option<DateTime<FixedOffset>> = match matches.value_of_os("like") {
+        Some(like) => source.datetime(Path::new(like))?,
+        None if source != DateTimeSource::Now => return Ok(None),
+        None => validated(matches, "datetime", StampFormat::parse_any)
+    };
+    Ok(Some(options.resolve_datetime(&date_time)))
+}
+
+fn file_datetime(path: &Path, date_time: &Option<DateTime<FixedOffset>>, source: DateTimeSource)
+    -> Result<Option<DateTime<FixedOffset>>, Error> {
+    // The datetime to stamp one file with.
+    match date_time {
+        Some(_) => Ok(*date_time),
+        None => source.datetime(path)
+    }
+}
+
+fn stamp_options(matches: &ArgMatches) -> StampOptions {
+    // Subcommands without --placement or --separator get the defaults.
+    StampOptions {
+        zone: stamp_zone(matches),
+        format: validated(matches, "stamp-format", StampFormat::from_name),
+        zulu: matches.is_present("zulu"),
+        placement: validated(matches, "placement", StampPlacement::from_name).unwrap_or_default(),
+        separator: validated(matches, "separator", StampSeparator::from_name).unwrap_or_default()
+    }
+}
+
+fn rename_or_print(path: &Path, new_path: &Path, matches: &ArgMatches) -> Result<(), Error> {
+    // Without --rename, prints the new name.  Otherwise renames the file; or with --dry-run, pretends to.
+    if !matches.is_present("rename") {
+        print_path(new_path);  //output the result to terminal
+        return Ok(());
+    }
+
+    let force: bool = matches.is_present("force");
+    if new_path == path {
+        // Already named correctly; such as a UTC stamp converted to UTC.  A missing file is still an error.
+        return filedate::stamp::check_rename(path, new_path, force);
+    }
+    if matches.is_present("dry-run") {
+        // Nothing is renamed.  But do warn about files that could not be renamed.
+        filedate::stamp::check_rename(path, new_path, force)?;
+        println!("[dry-run] {} -> {}", path.display(), new_path.display());
+        return Ok(());
+    }
+
+    filedate::stamp::rename_path(path, new_path, force)?;
+    println!("{} -> {}", path.display(), new_path.display());
+    Ok(())
+}
+
+fn input_paths(matches: &ArgMatches) -> Result<Vec<PathBuf>, Error> {
+    // The filename arguments, followed by any names read from stdin.
+    let mut paths: Vec<PathBuf> = matches.values_of_os("filename")
+        .map(|values| values.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    if matches.is_present("stdin") {
+        paths.extend(read_stdin_paths(matches.is_present("null"))?);
+    }
+    Ok(paths)
+}
+
+fn read_stdin_paths(null: bool) -> Result<Vec<PathBuf>, Error> {
+    // One name per line; or with --null, separated by NUL bytes (as from 'find -print0').  Empty names are skipped.
+    use std::io::Read;
+    let mut input: Vec<u8> = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut input).map_err(|error| Error::from_io(Path::new("-"), error))?;
+
+    let delimiter: u8 = if null { b'\0' } else { b'\n' };
+    input.split(|byte| *byte == delimiter)
+        .map(|name| if null { name } else { name.strip_suffix(b"\r").unwrap_or(name) })
+        .filter(|name| !name.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(name: &[u8]) -> Result<PathBuf, Error> {
+    // On Unix, any bytes are a valid path.  So names that are not UTF-8 survive a trip through a pipe.
+    use std::os::unix::ffi::OsStrExt;
+    Ok(PathBuf::from(std::ffi::OsStr::from_bytes(name)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(name: &[u8]) -> Result<PathBuf, Error> {
+    std::str::from_utf8(name)
+        .map(PathBuf::from)
+        .map_err(|_| Error::NotUtf8(PathBuf::from(String::from_utf8_lossy(name).into_owned())))
+}
+
+fn print_path(path: &Path) {
+    // On Unix, the path is printed as raw bytes.  So names that are not UTF-8 survive a trip through a pipe.
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::ffi::OsStrExt;
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(path.as_os_str().as_bytes()).and_then(|_| stdout.write_all(b"\n"));
+    }
+    #[cfg(not(unix))]
+    println!("{}", path.display());
+}
+
+fn walk_options(matches: &ArgMatches) -> WalkOptions {
+    // Without --recursive or --max-depth, only the directory's own files are used.
+    let patterns = |name: &str| -> Vec<glob::Pattern> {
+        matches.values_of(name)
+            .map(|values| values.map(|value| expect_valid(name, value, glob::Pattern::new(value).ok())).collect())
+            .unwrap_or_default()
+    };
+    let max_depth: Option<usize> = match validated(matches, "max-depth", |value| value.parse().ok()) {
+        Some(max_depth) => Some(max_depth),
+        None if matches.is_present("recursive") => None,
+        None => Some(1)
+    };
+    WalkOptions {
+        max_depth,
+        include: patterns("include"),
+        exclude: patterns("exclude"),
+        follow_symlinks: matches.is_present("follow-symlinks")
+    }
+}
+
+fn set_jobs(matches: &ArgMatches) {
+    // The number of threads used for walking directories and parsing names.  Only the first call has any effect.
+    let jobs: usize = validated(matches, "jobs", |value| value.parse().ok()).unwrap_or(0);
+    let _ = rayon::ThreadPoolBuilder::new().num_threads(jobs).build_global();
+}
+
+fn stamp_zone(matches: &ArgMatches) -> Option<StampZone> {
+    // The zone chosen with --utc, --local, or --offset.  None when the caller did not choose.
+    if matches.is_present("utc") {
+        return Some(StampZone::Utc);
+    }
+    if matches.is_present("local") {
+        return Some(StampZone::Local);
+    }
+    if let Some(zone) = named_zone(matches) {
+        return Some(StampZone::Named(zone));
+    }
+    validated(matches, "offset", filedate::dates::offset_from_string).map(StampZone::Fixed)
+}
+
+fn named_zone(matches: &ArgMatches) -> Option<Tz> {
+    validated(matches, "tz", |value| value.parse().ok())
+}
+
+fn cli_prune(prune_matches: &ArgMatches) -> i32 {
+    // Returns the exit code.  Nothing is deleted, unless the caller passed '--yes'.
+    let count_of = |name: &str| -> usize {
+        validated(prune_matches, name, |value| value.parse().ok()).unwrap_or(0)
     };
+    let policy = RetentionPolicy {
+        keep_last: count_of("keep-last"),
+        keep_daily: count_of("keep-daily"),
+        keep_weekly: count_of("keep-weekly"),
+        keep_monthly: count_of("keep-monthly"),
+        keep_yearly: count_of("keep-yearly")
+    };
+    if policy.is_empty() {
+        eprintln!("error: at least one --keep-* option is required");
+        return 1;
+    }
+
+    set_jobs(prune_matches);
+    let options: WalkOptions = walk_options(prune_matches);
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for directory in prune_matches.values_of_os("directory").unwrap() {
+        match filedate::walk::walk_directory(Path::new(directory), &options) {
+            Ok(walk) => {
+                report_warnings(&walk.warnings);
+                paths.extend(walk.files);
+            },
+            Err(err) => return report_error(&err, 0)
+        }
+    }
+    // Overlapping directories (such as 'backups -R backups/db') find the same files twice.
+    paths.sort();
+    paths.dedup();
+
+    let dated_paths: Vec<DatedPathBuf> = filedate::metadata::dated_paths(paths);
+    let plan: RetentionPlan = retention::apply_policy_to_dated(&policy, &dated_paths);
+    let dry_run: bool = !prune_matches.is_present("yes");
+
+    let mut exit_code = 0;
+    if let Err(err) = print_records(output_format(prune_matches, OutputFormat::Table), &plan.entries()) {
+        exit_code = report_error(&err, exit_code);
+    }
+    if dry_run && !plan.delete.is_empty() {
+        eprintln!("Dry run; nothing was deleted.  Pass --yes to delete {} file(s).", plan.delete.len());
+    }
+
+    if !dry_run {
+        for path in &plan.delete {
+            if let Err(err) = std::fs::remove_file(path) {
+                exit_code = report_error(&Error::from_io(path, err), exit_code);
+            }
+        }
+    }
+    exit_code
+}
+
+fn is_count(value: String) -> Result<(), String> {
+    // Clap validator for options that take a number of files, periods, or threads.  Zero is allowed.
+    match value.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("'{}' is not a whole number", value))
+    }
+}
+
+fn is_depth(value: String) -> Result<(), String> {
+    // Clap validator for --max-depth.  Zero is rejected; a walk always reads at least the directory's own files.
+    match value.parse::<usize>() {
+        Ok(depth) if depth > 0 => Ok(()),
+        _ => Err(format!("'{}' is not a positive whole number", value))
+    }
+}
+
+fn is_glob(value: String) -> Result<(), String> {
+    // Clap validator for --include and --exclude patterns.
+    match glob::Pattern::new(&value) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("'{}' is not a valid pattern: {}", value, err))
+    }
+}
+
+fn is_duration(value: String) -> Result<(), String> {
+    // Clap validator for lengths of time.
+    match filedate::dates::duration_from_string(&value) {
+        Some(_) => Ok(()),
+        None => Err(format!("'{}' is not a length of time like 90s, 5m, or 1h30m", value))
+    }
+}
+
+fn is_datetime(value: String) -> Result<(), String> {
+    // Clap validator for ISO 8601 datetimes, in any StampFormat.
+    match StampFormat::parse_any(&value) {
+        Some(_) => Ok(()),
+        None => Err(format!("'{}' is not an ISO 8601 datetime like 2020-02-27T175900-0800", value))
+    }
 }
 
-fn cli_add_suffix_no_date(filename_orig: &str) {
-    let new_filename: String = filedate::stamp::stamp_str(filename_orig, &None);
-    println!("{}", new_filename);  //output the result to terminal
+fn is_offset(value: String) -> Result<(), String> {
+    // Clap validator for UTC offsets.
+    match filedate::dates::offset_from_string(&value) {
+        Some(_) => Ok(()),
+        None => Err(format!("'{}' is not a UTC offset like +0530 or -0800", value))
+    }
+}
+
+fn is_named_zone(value: String) -> Result<(), String> {
+    // Clap validator for IANA time zone names.
+    match value.parse::<Tz>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("'{}' is not an IANA time zone like Europe/Berlin", value))
+    }
+}
+
+fn source_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // Where the datetime comes from.  Shared by the subcommands that write datetimes.
+    vec![
+        Arg::with_name("datetime")
+            .short("t")
+            .long("datetime")
+            .help("A datetime string, instead of the current datetime")
+            .takes_value(true)
+            .validator(is_datetime)
+            .conflicts_with_all(&["from", "like"]),
+        Arg::with_name("from")
+            .long("from")
+            .value_name("SOURCE")
+            .help("use the current datetime (default), or each file's modification, status-change, or birth time")
+            .possible_values(&["now", "mtime", "ctime", "btime"]),
+        Arg::with_name("like")
+            .long("like")
+            .value_name("FILE")
+            .help("use the datetime of another file, instead of each file's own (with --from, except now; default mtime)")
+    ]
+}
+
+fn zone_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // The zone to stamp in.  Shared by the subcommands that write datetimes.
+    vec![
+        Arg::with_name("utc")
+            .long("utc")
+            .help("stamp in UTC, instead of the local system's offset")
+            .conflicts_with_all(&["local", "offset", "tz"]),
+        Arg::with_name("local")
+            .long("local")
+            .help("stamp in the local system's offset (the default for the current datetime)")
+            .conflicts_with_all(&["offset", "tz"]),
+        Arg::with_name("tz")
+            .long("tz")
+            .value_name("ZONE")
+            .help("stamp in an IANA time zone, such as Europe/Berlin")
+            .conflicts_with("offset")
+            .validator(is_named_zone),
+        Arg::with_name("offset")
+            .long("offset")
+            .value_name("+HHMM")
+            .help("stamp with a fixed UTC offset, such as +0530")
+            .allow_hyphen_values(true)
+            .validator(is_offset)
+    ]
+}
+
+fn format_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // How to write datetimes.  Shared by the subcommands that write datetimes.
+    vec![
+        Arg::with_name("stamp-format")
+            .long("stamp-format")
+            .value_name("PROFILE")
+            .help("how to write the datetime (default: hybrid, like 2020-02-27T175900-0800; restamp keeps each file's own)")
+            .possible_values(&STAMP_FORMATS),
+        Arg::with_name("zulu")
+            .long("zulu")
+            .help("write UTC datetimes with a 'Z', instead of +0000")
+    ]
+}
+
+fn input_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // Where the filenames come from.  Shared by the subcommands that change file names.
+    vec![
+        Arg::with_name("stdin")
+            .long("stdin")
+            .help("also read filenames from stdin, one per line"),
+        Arg::with_name("null")
+            .short("0")
+            .long("null")
+            .help("filenames on stdin are separated by NUL bytes, as from 'find -print0'")
+            .requires("stdin")
+    ]
+}
+
+fn rename_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // Print the new names, or rename the files.  Shared by the subcommands that change file names.
+    vec![
+        Arg::with_name("rename")
+            .short("r")
+            .long("rename")
+            .help("rename the files on disk, instead of printing the new names"),
+        Arg::with_name("dry-run")
+            .short("n")
+            .long("dry-run")
+            .help("print what would be renamed, without renaming anything")
+            .requires("rename"),
+        Arg::with_name("force")
+            .short("f")
+            .long("force")
+            .help("overwrite existing files when renaming")
+            .requires("rename")
+    ]
+}
+
+fn output_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // How to print a list of results.  Shared by the subcommands that list files.
+    vec![
+        Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("how to print the results (default: json for extract, table otherwise)")
+            .possible_values(&["json", "ndjson", "csv", "tsv", "table"]),
+        Arg::with_name("json")
+            .long("json")
+            .help("same as --format json")
+            .conflicts_with("format")
+    ]
+}
+
+fn walk_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    // Which files to find beneath a directory.  Shared by the subcommands that read directories.
+    vec![
+        Arg::with_name("recursive")
+            .short("R")
+            .long("recursive")
+            .help("also read subdirectories, at any depth"),
+        Arg::with_name("max-depth")
+            .long("max-depth")
+            .value_name("N")
+            .help("read subdirectories, up to N levels deep (1 is only the directory's own files)")
+            .validator(is_depth),
+        Arg::with_name("include")
+            .long("include")
+            .value_name("GLOB")
+            .help("only use files matching this pattern, such as '*.tar.gz' (may be repeated)")
+            .multiple(true)
+            .number_of_values(1)
+            .validator(is_glob),
+        Arg::with_name("exclude")
+            .long("exclude")
+            .value_name("GLOB")
+            .help("skip files and directories matching this pattern (may be repeated)")
+            .multiple(true)
+            .number_of_values(1)
+            .validator(is_glob),
+        Arg::with_name("follow-symlinks")
+            .short("L")
+            .long("follow-symlinks")
+            .help("descend into symbolic links to directories; loops are skipped with a warning"),
+        Arg::with_name("jobs")
+            .short("j")
+            .long("jobs")
+            .value_name("N")
+            .help("read directories with N threads (default: 0, for 1 per CPU)")
+            .validator(is_count)
+    ]
 }
 
 fn add_arguments<'a, 'b>(cli_app: App<'a, 'b>) -> App<'a, 'b> {
@@ -51,13 +738,93 @@ fn add_arguments<'a, 'b>(cli_app: App<'a, 'b>) -> App<'a, 'b> {
         .subcommand(SubCommand::with_name("stamp")
             .about("stamps a filename with an ISO 8601 datetime")
             .arg(Arg::with_name("filename")
-                .help("The filename to stamp")
-                .required(true),
+                .help("One or more filenames to stamp")
+                .required_unless("stdin")
+                .multiple(true),
+            )
+            .args(&source_arguments())
+            .args(&zone_arguments())
+            .args(&format_arguments())
+            .arg(Arg::with_name("placement")
+                .long("placement")
+                .value_name("PLACEMENT")
+                .help("where the datetime goes: after the stem (default), at the front, at the end, or before the last extension")
+                .possible_values(&["stem", "front", "end", "last-ext"]),
+            )
+            .arg(Arg::with_name("separator")
+                .long("separator")
+                .value_name("SEPARATOR")
+                .help("what goes between the datetime and the rest of the file name (default '_'; '.' cannot be used with --placement front)")
+                .possible_values(&["_", "__", "-", ".", "@"]),
             )
-            .arg(Arg::with_name("datetime")
-                .help("A datetime string")
-                .required(false),
-            )      
+            .args(&input_arguments())
+            .args(&rename_arguments())
+        )
+        .subcommand(SubCommand::with_name("restamp")
+            .about("replaces the ISO 8601 datetime already in a filename")
+            .arg(Arg::with_name("filename")
+                .help("One or more stamped filenames")
+                .required_unless("stdin")
+                .multiple(true),
+            )
+            .args(&source_arguments())
+            .arg(Arg::with_name("keep-datetime")
+                .long("keep-datetime")
+                .help("keep the same instant, only converting its zone or format (for example, with --utc)")
+                .conflicts_with_all(&["datetime", "from", "like"]),
+            )
+            .args(&zone_arguments())
+            .args(&format_arguments())
+            .args(&input_arguments())
+            .args(&rename_arguments())
+        )
+        .subcommand(SubCommand::with_name("unstamp")
+            .about("removes the ISO 8601 datetime from a filename")
+            .arg(Arg::with_name("filename")
+                .help("One or more stamped filenames")
+                .required_unless("stdin")
+                .multiple(true),
+            )
+            .args(&input_arguments())
+            .args(&rename_arguments())
+        )
+        .subcommand(SubCommand::with_name("touch")
+            .about("sets each file's modification time to the ISO 8601 datetime in its filename")
+            .arg(Arg::with_name("filename")
+                .help("One or more stamped filenames, or directories of them")
+                .required_unless("stdin")
+                .multiple(true),
+            )
+            .arg(Arg::with_name("atime")
+                .short("a")
+                .long("atime")
+                .help("also set the access time"),
+            )
+            .arg(Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("print what would be changed, without changing anything"),
+            )
+            .args(&input_arguments())
+            .args(&walk_arguments())
+            .args(&output_arguments())
+        )
+        .subcommand(SubCommand::with_name("verify")
+            .about("compares the ISO 8601 datetime in each filename with the file's modification time")
+            .arg(Arg::with_name("filename")
+                .help("One or more stamped filenames, or directories of them")
+                .required_unless("stdin")
+                .multiple(true),
+            )
+            .arg(Arg::with_name("tolerance")
+                .long("tolerance")
+                .value_name("DURATION")
+                .help("the largest difference that is not drift, such as 90s, 5m, or 1h30m (default 1s)")
+                .validator(is_duration),
+            )
+            .args(&output_arguments())
+            .args(&input_arguments())
+            .args(&walk_arguments())
         )
         .subcommand(SubCommand::with_name("extract")
             .about("extract metadata from a path or filename")
@@ -71,6 +838,51 @@ fn add_arguments<'a, 'b>(cli_app: App<'a, 'b>) -> App<'a, 'b> {
                 .help("verify that path exists")
                 .takes_value(false),
             )
+            .arg(Arg::with_name("tz")
+                .long("tz")
+                .value_name("ZONE")
+                .help("also express each datetime in an IANA time zone, such as Europe/Berlin")
+                .validator(is_named_zone),
+            )
+            .args(&walk_arguments())
+            .arg(Arg::with_name("sort")
+                .long("sort")
+                .value_name("ORDER")
+                .help("order a directory's files by path (default), or by datetime")
+                .possible_values(&["path", "datetime"]),
+            )
+            .args(&output_arguments())
+        )
+        .subcommand(SubCommand::with_name("prune")
+            .about("deletes old stamped files, according to a retention policy")
+            .arg(Arg::with_name("directory")
+                .help("One or more directories of stamped files")
+                .required(true)
+                .multiple(true),
+            )
+            .arg(Arg::with_name("keep-last").long("keep-last").value_name("N").validator(is_count)
+                .help("keep the N newest files"))
+            .arg(Arg::with_name("keep-daily").long("keep-daily").value_name("N").validator(is_count)
+                .help("keep the newest file per day, for the N most recent days that have files"))
+            .arg(Arg::with_name("keep-weekly").long("keep-weekly").value_name("N").validator(is_count)
+                .help("keep the newest file per ISO week, for the N most recent weeks that have files"))
+            .arg(Arg::with_name("keep-monthly").long("keep-monthly").value_name("N").validator(is_count)
+                .help("keep the newest file per month, for the N most recent months that have files"))
+            .arg(Arg::with_name("keep-yearly").long("keep-yearly").value_name("N").validator(is_count)
+                .help("keep the newest file per year, for the N most recent years that have files"))
+            .arg(Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("print what would be deleted, without deleting anything (the default)"),
+            )
+            .arg(Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("actually delete the files")
+                .conflicts_with("dry-run"),
+            )
+            .args(&output_arguments())
+            .args(&walk_arguments())
         );
     ret
 }
@@ -92,30 +904,27 @@ fn main() {
 
     match matches.subcommand() {
         ("stamp", Some(stamp_matches)) => {
-            let filename = stamp_matches.value_of("filename").unwrap();
-            if stamp_matches.is_present("datetime") {
-                cli_add_suffix(filename, stamp_matches.value_of("datetime").unwrap());
-            } else {
-                cli_add_suffix_no_date(filename);
-            }
+            std::process::exit(cli_stamp(stamp_matches));
         },
         ("extract", Some(extract_matches)) => {
-            let path = Path::new(extract_matches.value_of("path").unwrap());
-            let check : bool = extract_matches.is_present("check");
-            let ret = filedate::build_metadata_from_path(path, check);
-            std::process::exit(match ret {
-                Ok(_) => {
-                    println!("{:?}", ret.unwrap());
-                    0
-                },
-                Err(err) => {
-                    eprintln!("error: {:?}", err);
-                    1
-                }
-            });
-            
+            std::process::exit(cli_extract(extract_matches));
+        },
+        ("restamp", Some(restamp_matches)) => {
+            std::process::exit(cli_restamp(restamp_matches));
+        },
+        ("unstamp", Some(unstamp_matches)) => {
+            std::process::exit(cli_unstamp(unstamp_matches));
+        },
+        ("prune", Some(prune_matches)) => {
+            std::process::exit(cli_prune(prune_matches));
+        },
+        ("touch", Some(touch_matches)) => {
+            std::process::exit(cli_touch(touch_matches));
+        },
+        ("verify", Some(verify_matches)) => {
+            std::process::exit(cli_verify(verify_matches));
         },
-        ("", None) => println!("Please specify a subcommand (stamp, extract)"), // If no subcommand was used it'll match the tuple ("", None)
+        ("", None) => println!("Please specify a subcommand (stamp, restamp, unstamp, touch, verify, extract, prune)"), // If no subcommand was used it'll match the tuple ("", None)
         _ => unreachable!(), // If all subcommands are defined above, anything else is unreachable!()
     }
 }
@@ -1,11 +1,16 @@
 // dates.rs
+// This module is about finding, parsing, and formatting the ISO 8601 datetimes inside file names.
 use std::path::Path;
+use std::sync::OnceLock;
 
 // Third Party
 use chrono::prelude::*;
-use regex::Regex;
+use regex::bytes::Regex as BytesRegex;
 
-const ISO_FORMAT: &'static str = "%Y-%m-%dT%H%M%S%z";
+use super::Error;
+use super::osbytes::os_str_bytes;
+
+const ISO_FORMAT: &str = "%Y-%m-%dT%H%M%S%z";  // The 'hybrid' StampFormat
 
 // Created an 'Extension Trait', that teaches DateTime types how to become iso_strings! :)
 trait Suffix8601Ext<'a> {
@@ -25,7 +30,169 @@ impl<'a> Suffix8601Ext<'a> for DateTime<FixedOffset> {
 impl<'a> Suffix8601Ext<'a> for DateTime<Utc> {
     fn to_iso_string(&self) -> String {
         // to_rfc3339_opts() returns an owned String
-        self.to_rfc3339_opts(SecondsFormat::Secs, false).replace(":","") 
+        self.to_rfc3339_opts(SecondsFormat::Secs, false).replace(':', "")
+    }
+}
+
+
+// Pieces of the regular expressions used by StampFormat.
+const YEAR: &str = "[0-9]{4}";
+const MONTH: &str = "(1[0-2]|0[1-9])";
+const DAY: &str = "(3[01]|0[1-9]|[12][0-9])";
+const HOUR: &str = "(2[0-3]|[01][0-9])";
+const MINUTE: &str = "([0-5][0-9])";  // also seconds
+const OFFSET_BASIC: &str = "(Z|[+-](2[0-3]|[01][0-9])[0-5][0-9])";
+const FRACTION: &str = "(\\.[0-9]+)?";
+const OFFSET_EXTENDED: &str = "(Z|[+-](2[0-3]|[01][0-9]):[0-5][0-9])";
+
+
+// Named profiles for writing (and reading) the datetime in a file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StampFormat {
+    #[default]
+    Hybrid,  // 2020-02-27T175900-0800  Extended date; basic time and offset.  No colons.
+    Basic,  // 20200227T175900-0800
+    Extended,  // 2020-02-27T17:59:00-08:00  Beware; colons are problematic in file names.
+    Minutes,  // 2020-02-27T1759-0800
+    DateOnly,  // 2020-02-27
+    Milliseconds,  // 2020-02-27T175900.123-0800
+    Microseconds  // 2020-02-27T175900.123456-0800
+}
+
+impl StampFormat {
+    // Every profile.  When searching a file name, the longest match wins.
+    pub const ALL: [StampFormat; 7] = [
+        StampFormat::Hybrid, StampFormat::Basic, StampFormat::Extended, StampFormat::Minutes,
+        StampFormat::DateOnly, StampFormat::Milliseconds, StampFormat::Microseconds
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StampFormat::Hybrid => "hybrid",
+            StampFormat::Basic => "basic",
+            StampFormat::Extended => "extended",
+            StampFormat::Minutes => "minutes",
+            StampFormat::DateOnly => "date",
+            StampFormat::Milliseconds => "millis",
+            StampFormat::Microseconds => "micros"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StampFormat> {
+        StampFormat::ALL.iter().find(|format| format.name() == name).copied()
+    }
+
+    pub fn format(&self, date_time: &DateTime<FixedOffset>, zulu: bool) -> String {
+        // When 'zulu' is true, UTC datetimes end with the 'Z' designator instead of '+0000'
+        self.format_with_fraction(date_time, zulu, 0)
+    }
+
+    pub fn format_with_fraction(&self, date_time: &DateTime<FixedOffset>, zulu: bool, digits: usize) -> String {
+        // Same as format(), but profiles with whole seconds also write 'digits' of fractional seconds (at most 9).
+        // For rewriting a datetime with the same precision it was read with, such as '2024-01-02T030405.1Z'.
+        let mut ret: String = date_time.format(self.datetime_format()).to_string();
+        if digits > 0 && matches!(self, StampFormat::Hybrid | StampFormat::Basic | StampFormat::Extended) {
+            let digits: u32 = digits.min(9) as u32;
+            let fraction: u32 = date_time.nanosecond() % 1_000_000_000 / 10u32.pow(9 - digits);
+            ret.push_str(&format!(".{:0width$}", fraction, width = digits as usize));
+        }
+        match self.offset_format() {
+            None => {},
+            Some(_) if zulu && date_time.offset().local_minus_utc() == 0 => ret.push('Z'),
+            Some(offset_format) => ret.push_str(&date_time.format(offset_format).to_string())
+        }
+        ret
+    }
+
+    pub fn parse(&self, value: &str) -> Option<DateTime<FixedOffset>> {
+        // Fractional seconds of any precision are accepted, except by 'minutes' and 'date'.
+        // Date-only stamps have no time or offset, so are treated as midnight UTC.
+        if self.offset_format().is_none() {
+            let date: NaiveDate = NaiveDate::parse_from_str(value, self.parse_format()).ok()?;
+            return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?).into());
+        }
+        match value.strip_suffix('Z') {
+            Some(naive) => {
+                let naive: NaiveDateTime = NaiveDateTime::parse_from_str(naive, self.parse_format()).ok()?;
+                Some(Utc.from_utc_datetime(&naive).into())
+            },
+            None => DateTime::parse_from_str(value, self.parse_format_with_offset()).ok()
+        }
+    }
+
+    pub fn parse_any(value: &str) -> Option<DateTime<FixedOffset>> {
+        // Parses a datetime written in any of the profiles.
+        StampFormat::ALL.iter().find_map(|format| format.parse(value))
+    }
+
+    pub fn find(&self, value: &str) -> Option<(usize, usize)> {
+        // Returns the byte positions where the first datetime in this profile begins and ends.
+        self.find_bytes(value.as_bytes())
+    }
+
+    pub fn find_bytes(&self, value: &[u8]) -> Option<(usize, usize)> {
+        // Same as find(), for file names that are not necessarily UTF-8.
+        // A date followed by 'T' and a digit is the start of a datetime, in a form no profile accepts
+        // (such as '2024-01-02T030405,5Z').  Reading it as a date-only stamp would silently lose the time.
+        format_regexes()[*self as usize].find_iter(value)
+            .find(|match_loc| *self != StampFormat::DateOnly || !is_time_designator(&value[match_loc.end()..]))
+            .map(|match_loc| (match_loc.start(), match_loc.end()))
+    }
+
+    fn datetime_format(&self) -> &'static str {
+        // Format string for chrono, without the offset.
+        match self {
+            StampFormat::Hybrid => "%Y-%m-%dT%H%M%S",
+            StampFormat::Basic => "%Y%m%dT%H%M%S",
+            StampFormat::Extended => "%Y-%m-%dT%H:%M:%S",
+            StampFormat::Minutes => "%Y-%m-%dT%H%M",
+            StampFormat::DateOnly => "%Y-%m-%d",
+            StampFormat::Milliseconds => "%Y-%m-%dT%H%M%S%.3f",
+            StampFormat::Microseconds => "%Y-%m-%dT%H%M%S%.6f"
+        }
+    }
+
+    fn parse_format(&self) -> &'static str {
+        // Same as datetime_format(), but '%.f' accepts an optional fraction of any precision.
+        match self {
+            StampFormat::Hybrid | StampFormat::Milliseconds | StampFormat::Microseconds => "%Y-%m-%dT%H%M%S%.f",
+            StampFormat::Basic => "%Y%m%dT%H%M%S%.f",
+            StampFormat::Extended => "%Y-%m-%dT%H:%M:%S%.f",
+            StampFormat::Minutes | StampFormat::DateOnly => self.datetime_format()
+        }
+    }
+
+    fn parse_format_with_offset(&self) -> &'static str {
+        // Same as parse_format(), followed by offset_format().  Spelled out, so that parsing never allocates.
+        match self {
+            StampFormat::Hybrid | StampFormat::Milliseconds | StampFormat::Microseconds => "%Y-%m-%dT%H%M%S%.f%z",
+            StampFormat::Basic => "%Y%m%dT%H%M%S%.f%z",
+            StampFormat::Extended => "%Y-%m-%dT%H:%M:%S%.f%:z",
+            StampFormat::Minutes => "%Y-%m-%dT%H%M%z",
+            StampFormat::DateOnly => self.parse_format()
+        }
+    }
+
+    fn offset_format(&self) -> Option<&'static str> {
+        match self {
+            StampFormat::DateOnly => None,
+            StampFormat::Extended => Some("%:z"),
+            _ => Some("%z")
+        }
+    }
+
+    fn pattern(&self) -> String {
+        // Regular expression for finding this profile inside a file name.
+        let date_extended: String = format!("{}-{}-{}", YEAR, MONTH, DAY);
+        match self {
+            StampFormat::Hybrid => format!("{}T{}{}{}{}{}", date_extended, HOUR, MINUTE, MINUTE, FRACTION, OFFSET_BASIC),
+            StampFormat::Basic => format!("{}{}{}T{}{}{}{}{}", YEAR, MONTH, DAY, HOUR, MINUTE, MINUTE, FRACTION, OFFSET_BASIC),
+            StampFormat::Extended => format!("{}T{}:{}:{}{}{}", date_extended, HOUR, MINUTE, MINUTE, FRACTION, OFFSET_EXTENDED),
+            StampFormat::Minutes => format!("{}T{}{}{}", date_extended, HOUR, MINUTE, OFFSET_BASIC),
+            StampFormat::DateOnly => date_extended,
+            StampFormat::Milliseconds => format!("{}T{}{}{}\\.[0-9]{{3}}{}", date_extended, HOUR, MINUTE, MINUTE, OFFSET_BASIC),
+            StampFormat::Microseconds => format!("{}T{}{}{}\\.[0-9]{{6}}{}", date_extended, HOUR, MINUTE, MINUTE, OFFSET_BASIC)
+        }
     }
 }
 
@@ -33,79 +200,205 @@ impl<'a> Suffix8601Ext<'a> for DateTime<Utc> {
 pub fn is_path_datetime_stamped<S>(path: S) -> bool
     where S: AsRef<Path> {
     // Checks if a Path contains a valid ISO 8601 datetime string.
-    let iso_regex = Regex::new(iso8601_pattern());
-    if iso_regex.is_err() {
-        return false
-    }
-    iso_regex.unwrap().is_match(path.as_ref().to_str().unwrap())
+    path_datetime_indices(path.as_ref()).is_some()
 }
 
 pub fn path_datetime_indices(path: &Path) -> Option<(usize, usize)> {
+    // Returns the byte positions where an ISO 8601 datetime begins and ends, within the entire Path.
+    // On Unix, the Path does not need to be UTF-8.
+    bytes_datetime_indices(os_str_bytes(path.as_os_str())?)
+}
 
-    // Checks if the Path contains a valid ISO 8601 datetime string.
-    let iso_regex = Regex::new(iso8601_pattern());
-    if iso_regex.is_err() {
-        return None
-    }
-    let match_loc = iso_regex.unwrap().find(path.to_str().unwrap());
-    if match_loc.is_none() {
-        return None;
-    }
-    Some((
-        match_loc.unwrap().start(), match_loc.unwrap().end()
-    ))
+pub fn str_datetime_indices(value: &str) -> Option<(usize, usize)> {
+    // Same as path_datetime_indices(), but for a string slice.
+    bytes_datetime_indices(value.as_bytes())
+}
+
+pub fn bytes_datetime_indices(value: &[u8]) -> Option<(usize, usize)> {
+    // Same as path_datetime_indices(), but for bytes.  Any StampFormat is accepted, the same as find_stamp().
+    super::find_stamp_bytes(value).map(|(start, end, _)| (start, end))
 }
 
 
-pub fn datetime_from_iso_string(dt_as_string: &str) -> Option<DateTime<FixedOffset>> {
-    const FMT: &str = "%Y-%m-%dT%H%M%S%z";
-    match DateTime::parse_from_str(dt_as_string, FMT) {
-        #[allow(unused_variables)]
-        Err(e) => {
-            return None;
-        }
-        Ok(f) => {
-            return Some(f);
-        }                
-    };
+pub fn datetime_from_iso_string(dt_as_string: &str) -> Result<DateTime<FixedOffset>, Error> {
+    // Also accepts fractional seconds of any precision, and the 'Z' designator for UTC.
+    StampFormat::Hybrid.parse(dt_as_string).ok_or_else(|| Error::InvalidDateTime(dt_as_string.to_owned()))
+}
+
+
+pub fn fractional_digits(dt_as_string: &str) -> usize {
+    // The precision of a datetime string's fractional seconds.  Zero when there are none.
+    match dt_as_string.split_once('.') {
+        Some((_, fraction)) => fraction.bytes().take_while(|byte| byte.is_ascii_digit()).count(),
+        None => 0
+    }
 }
 
 
 pub fn datetime_to_iso_string(parm_date_time: &Option<DateTime<FixedOffset>>) -> String {
 	// Returns on owned String, that represents an ISO 8601 datetime value.
-	if parm_date_time.is_some() {
-		parm_date_time.unwrap().to_iso_string();
+	// Without an argument, the current datetime in the local system's offset.
+	match parm_date_time {
+		Some(date_time) => date_time.to_iso_string(),
+		None => DateTime::<FixedOffset>::from(Local::now()).to_iso_string()
 	}
-	else {
-		Utc::now().to_iso_string();
-	}        
-}	
+}
+
+
+pub fn offset_from_string(offset_as_string: &str) -> Option<FixedOffset> {
+    // Parses a UTC offset such as '+0530', '+05:30', '-08', or 'Z'.
+    // Only ±HH, ±HHMM, and ±HH:MM are accepted; a colon anywhere else (like '+05:' or '+0:530') is not an offset.
+    if offset_as_string == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let (sign, digits): (i32, &[u8]) = match offset_as_string.as_bytes() {
+        [b'+', rest @ ..] => (1, rest),
+        [b'-', rest @ ..] => (-1, rest),
+        _ => return None
+    };
+    let (hours, minutes): (&[u8], &[u8]) = match digits {
+        [_, _] => (digits, b"00"),
+        [_, _, _, _] => digits.split_at(2),
+        [_, _, b':', _, _] => (&digits[..2], &digits[3..]),
+        _ => return None
+    };
+    let two_digits = |value: &[u8]| -> Option<i32> {
+        match value {
+            [tens, ones] if tens.is_ascii_digit() && ones.is_ascii_digit() => Some(i32::from((tens - b'0') * 10 + (ones - b'0'))),
+            _ => None
+        }
+    };
+    let (hours, minutes): (i32, i32) = (two_digits(hours)?, two_digits(minutes)?);
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+pub fn duration_from_string(duration_as_string: &str) -> Option<chrono::Duration> {
+    // Parses a length of time such as '5m', '90s', '1h30m', or '250ms'.  A bare number is seconds.
+    // The units are ms, s, m, h, d (days), and w (weeks).
+    if let Ok(seconds) = duration_as_string.parse::<u32>() {
+        return Some(chrono::Duration::seconds(seconds.into()));
+    }
+    if duration_as_string.is_empty() {
+        return None;
+    }
+
+    let mut milliseconds: i64 = 0;
+    let mut rest: &str = duration_as_string;
+    while !rest.is_empty() {
+        let digits_end: usize = rest.find(|c: char| !c.is_ascii_digit()).filter(|index| *index > 0)?;
+        let amount: i64 = rest[..digits_end].parse().ok()?;
+        rest = &rest[digits_end..];
+        let unit_end: usize = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit: i64 = match &rest[..unit_end] {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 3600 * 1000,
+            "d" => 86400 * 1000,
+            "w" => 7 * 86400 * 1000,
+            _ => return None
+        };
+        milliseconds = milliseconds.checked_add(amount.checked_mul(unit)?)?;
+        rest = &rest[unit_end..];
+    }
+    Some(chrono::Duration::milliseconds(milliseconds))
+}
 
 // ---- PRIVATE FUNCTIONS -----
 
-fn iso8601_pattern() -> &'static str {
-    // Return a regular expression for matcing ISO 8601 datetime format.
-    // Using the 'concat' macro so I can document the components of the expression.
-    concat!(
-         "([0-9]{4})"           // year
-        ,"-?(1[0-2]|0[1-9])"    // month
-        ,"-?(3[01]|0[1-9]|[12][0-9])" // day
-        ,"T" // date-time seperator
-        ,"(2[0-3]|[01][0-9])"  // hour
-        ,":?([0-5][0-9])"  // minute
-        ,":?([0-5][0-9])"  // second
-        ,"-?(2[0-3]|[01][0-9])" // offset hour
-        ,":?([0-5][0-9])"  // offset minute
-    )
+fn format_regexes() -> &'static [BytesRegex; 7] {
+    // Compiled once, on first use.  In the same order as StampFormat::ALL, which is also declaration order.
+    static REGEXES: OnceLock<[BytesRegex; 7]> = OnceLock::new();
+    REGEXES.get_or_init(|| StampFormat::ALL.map(|format| {
+        BytesRegex::new(&format.pattern()).expect("StampFormat patterns are constant, and valid")
+    }))
+}
+
+fn is_time_designator(value: &[u8]) -> bool {
+    // True when 'value' begins with the 'T' that separates a date from its time.
+    matches!(value, [b'T', digit, ..] if digit.is_ascii_digit())
 }
 
 
 // ----UNIT TESTS----
 
+#[test]
+fn test_stamp_format_order() {
+    // format_regexes() relies on StampFormat::ALL being in declaration order.
+    for (index, format) in StampFormat::ALL.iter().enumerate() {
+        assert_eq!(*format as usize, index);
+    }
+}
+
+#[test]
+fn test_stamp_formats() {
+    let date_time: DateTime<FixedOffset> = DateTime::parse_from_rfc3339("2020-02-27T17:59:00.123456-08:00").unwrap();
+    let expected = [ (StampFormat::Hybrid, "2020-02-27T175900-0800"),
+                     (StampFormat::Basic, "20200227T175900-0800"),
+                     (StampFormat::Extended, "2020-02-27T17:59:00-08:00"),
+                     (StampFormat::Minutes, "2020-02-27T1759-0800"),
+                     (StampFormat::DateOnly, "2020-02-27"),
+                     (StampFormat::Milliseconds, "2020-02-27T175900.123-0800"),
+                     (StampFormat::Microseconds, "2020-02-27T175900.123456-0800") ];
+
+    for (format, stamp) in expected.iter() {
+        assert_eq!( format.format(&date_time, false), *stamp );
+        assert_eq!( format.find(&format!("db_{}.sql", stamp)), Some((3, 3 + stamp.len())) );
+        assert!( format.parse(stamp).is_some() );
+    }
+    assert_eq!( StampFormat::Microseconds.parse("2020-02-27T175900.123456-0800"), Some(date_time) );
+    assert_eq!( StampFormat::DateOnly.parse("2020-02-27").unwrap().to_rfc3339(), "2020-02-27T00:00:00+00:00" );
+
+    // The 'Z' designator is only written for UTC datetimes, and only when asked.
+    let utc_time: DateTime<FixedOffset> = date_time.with_timezone(&Utc).into();
+    assert_eq!( StampFormat::Hybrid.format(&utc_time, true), "2020-02-28T015900Z" );
+    assert_eq!( StampFormat::Hybrid.format(&utc_time, false), "2020-02-28T015900+0000" );
+    assert_eq!( StampFormat::Hybrid.format(&date_time, true), "2020-02-27T175900-0800" );
+    assert_eq!( StampFormat::Hybrid.parse("2020-02-28T015900Z"), Some(utc_time.with_nanosecond(0).unwrap()) );
+}
+
+#[test]
+fn test_fractional_seconds() {
+    // Other tools write fractional seconds of any precision, and 'Z' for UTC.
+    for (dt_as_string, digits) in [("2024-01-02T030405.1Z", 1), ("2024-01-02T030405.123Z", 3),
+                                   ("2024-01-02T030405.1234567-0800", 7), ("2024-01-02T030405+0100", 0)] {
+        assert!( is_path_datetime_stamped(format!("dump_{}.sql", dt_as_string)) );
+        assert!( datetime_from_iso_string(dt_as_string).is_ok() );
+        assert_eq!( fractional_digits(dt_as_string), digits );
+    }
+    assert_eq!( datetime_from_iso_string("2024-01-02T030405.123Z").unwrap().to_rfc3339(), "2024-01-02T03:04:05.123+00:00" );
+    assert_eq!( datetime_from_iso_string("2024-01-02T030405.1234567-0800").unwrap().nanosecond(), 123_456_700 );
+}
+
+#[test]
+fn test_offset_from_string() {
+    assert_eq!( offset_from_string("+0530"), FixedOffset::east_opt(5 * 3600 + 30 * 60) );
+    assert_eq!( offset_from_string("+05:30"), FixedOffset::east_opt(5 * 3600 + 30 * 60) );
+    assert_eq!( offset_from_string("-08"), FixedOffset::west_opt(8 * 3600) );
+    assert_eq!( offset_from_string("Z"), FixedOffset::east_opt(0) );
+    for invalid in ["", "+", "0530", "+05:", "+0:530", "+053:0", ":+0530", "+05::30", "+5", "+24", "+0560", "+05:3", "+0530Z", "+٠٥"] {
+        assert_eq!( offset_from_string(invalid), None, "{}", invalid );
+    }
+}
+
+#[test]
+fn test_duration_from_string() {
+    assert_eq!( duration_from_string("5m"), Some(chrono::Duration::minutes(5)) );
+    assert_eq!( duration_from_string("90"), Some(chrono::Duration::seconds(90)) );
+    assert_eq!( duration_from_string("1h30m"), Some(chrono::Duration::minutes(90)) );
+    assert_eq!( duration_from_string("2d250ms"), Some(chrono::Duration::days(2) + chrono::Duration::milliseconds(250)) );
+    for invalid in ["", "m", "5x", "1h30", "-5m", "5 m"] {
+        assert_eq!( duration_from_string(invalid), None, "{}", invalid );
+    }
+}
+
 #[test]
 fn test_get_datetime_string() {
     
-    let dt_naive: NaiveDateTime = NaiveDate::from_ymd(1955, 11, 5).and_hms(9, 10, 11);
+    let dt_naive: NaiveDateTime = NaiveDate::from_ymd_opt(1955, 11, 5).unwrap().and_hms_opt(9, 10, 11).unwrap();
 
     fn dtnaive_to_dtfixed(dt_naive: NaiveDateTime,
                                  tz_offset: FixedOffset) -> DateTime<FixedOffset> {
@@ -114,30 +407,30 @@ fn test_get_datetime_string() {
 
     // Scenario 1
     {
-        let no_offset = FixedOffset::west(0);
+        let no_offset = FixedOffset::west_opt(0).unwrap();
         let dt_fixed: DateTime<FixedOffset> = dtnaive_to_dtfixed(dt_naive, no_offset);
         assert_eq!( datetime_to_iso_string(&Some(dt_fixed)), "1955-11-05T091011+0000");
     }
 
     // Scenario 2
     {
-        let offset = FixedOffset::west(8 * 3600);
+        let offset = FixedOffset::west_opt(8 * 3600).unwrap();
         let dt_fixed: DateTime<FixedOffset> = dtnaive_to_dtfixed(dt_naive, offset);
-        assert_eq!( datetime_to_iso_string(&Some(dt_fixed)), "1955-11-05T091011+0000");
+        assert_eq!( datetime_to_iso_string(&Some(dt_fixed)), "1955-11-05T091011-0800");
     }
 
     // Scenario 3
     {
-        let utc_datetime: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
-        let no_offset = FixedOffset::west(0);
+        let utc_datetime: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
+        let no_offset = FixedOffset::west_opt(0).unwrap();
         let utc_minus_0 = utc_datetime.with_timezone(&no_offset);
         assert_eq!( datetime_to_iso_string(&Some(utc_minus_0)), "1955-11-05T091011+0000");
     }
 
     // Scenario 4
     {
-        let utc_datetime: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
-        let offset = FixedOffset::west(8 * 3600);
+        let utc_datetime: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
+        let offset = FixedOffset::west_opt(8 * 3600).unwrap();
         let utc_minus_8 = utc_datetime.with_timezone(&offset);
         assert_eq!( self::datetime_to_iso_string(&Some(utc_minus_8)), "1955-11-05T011011-0800");
     }        
@@ -0,0 +1,63 @@
+// error.rs
+// This module is about the errors returned by the filedate library.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+
+#[derive(Debug)]
+pub enum Error {
+    NotUtf8(PathBuf),  // The path's file name cannot be represented by a UTF-8 String.
+    NoStamp(String),  // The file name does not contain an ISO 8601 datetime.
+    InvalidDateTime(String),  // The string is not a valid ISO 8601 datetime.
+    NotFound(PathBuf),
+    AlreadyExists(PathBuf),  // Refusing to replace an existing file.
+    InvalidFileName(String),  // Such as '.', a path without a file name, or a name that is only a datetime.
+    SymlinkLoop(PathBuf),  // A symbolic link to a directory that contains it.
+    Io(PathBuf, io::Error),  // Any other filesystem error.
+    Json(serde_json::Error)
+}
+
+impl Error {
+    pub fn from_io(path: &Path, error: io::Error) -> Error {
+        // Attaches the path to a filesystem error.  Missing files become NotFound.
+        match error.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(path.to_path_buf()),
+            io::ErrorKind::AlreadyExists => Error::AlreadyExists(path.to_path_buf()),
+            _ => Error::Io(path.to_path_buf(), error)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotUtf8(path) => write!(f, "Path does not end with a UTF-8 file name: '{}'", path.display()),
+            Error::NoStamp(file_name) => write!(f, "File name has no ISO 8601 datetime: '{}'", file_name),
+            Error::InvalidDateTime(value) => write!(f, "Not a valid ISO 8601 datetime: '{}'", value),
+            Error::NotFound(path) => write!(f, "No such file or directory: '{}'", path.display()),
+            Error::AlreadyExists(path) => write!(f, "Target already exists: '{}'", path.display()),
+            Error::InvalidFileName(file_name) => write!(f, "Invalid file name: '{}'", file_name),
+            Error::SymlinkLoop(path) => write!(f, "Symbolic link loop: '{}'", path.display()),
+            Error::Io(path, error) => write!(f, "'{}': {}", path.display(), error),
+            Error::Json(error) => write!(f, "Received a JSON encoding error: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, error) => Some(error),
+            Error::Json(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Json(error)
+    }
+}
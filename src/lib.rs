@@ -1,18 +1,32 @@
 // lib.rs
 
-use std::fs;  // for reading directories.
-use std::path::{Path, PathBuf};
-use json::{JsonValue, object, Error as JSONError};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::path::Path;
 
 // Third Party crates
 use chrono::prelude::*;
-use regex::Regex;
+use chrono_tz::Tz;
 #[allow(unused_imports)]
 use serde::ser::{Serializer, SerializeStruct};
-#[allow(unused_imports)]
 // use serde_json::Result as ResultSerde;
 
+pub mod dates;
+pub mod error;
+pub mod metadata;
+mod osbytes;
+pub mod output;
+pub mod retention;
 pub mod stamp;
+pub mod verify;
+pub mod walk;
+
+use dates::StampFormat;
+pub use error::Error;
+use stamp::StampSeparator;
+
+use metadata::{DatedPathBuf, FileNameMeta};
+use walk::WalkOptions;
 
 pub fn get_package_version() -> &'static str {
     // Completed.
@@ -22,7 +36,7 @@ pub fn get_package_version() -> &'static str {
 
 // --------
 
-pub fn print_path_parts(path: &Path) -> () {
+pub fn print_path_parts(path: &Path) {
     println!("is_dir: {}", path.is_dir());
     println!("file_name {:?}", path.file_name());
 
@@ -53,140 +67,243 @@ struct DatedPath {
     path: Path
 }
 
-// Same as PathBuf, but must contain an ISO 8601 datetime in the stem.
-struct DatedPathBuf {
-    pathbuf: PathBuf
-}
-
-impl DatedPathBuf {
-
-    fn new_from_pathbuf(path_buffer: PathBuf) -> Result<DatedPathBuf, &'static str> {
-        if path_buffer.to_str().is_none() {
-            return Err("Path cannot be represented by a UTF8 String.");
-        }
 
-        if ! self::is_path_file8601(path_buffer.as_path()) {
-            return Err("Path does not contain an ISO 8601 datetime.");
-        }
+pub fn datetime_from_string(dt_as_string: &str) -> Result<DateTime<FixedOffset>, Error> {
+    dates::datetime_from_iso_string(dt_as_string)
+}
 
-        Ok(DatedPathBuf {
-            pathbuf: path_buffer
-        })
-    }
 
-    #[allow(dead_code)]
-    fn json_metadata(&self) -> Result<JsonValue, JSONError> {
-        let mut data: JsonValue = json::JsonValue::new_array();
-
-        let inner = object!{
-            name: self.pathbuf.to_str().unwrap(),
-            age: 30,
-            is_programmer: true
-        };
-        data.push(inner)?;
-        Ok(data)
-    }
+pub fn build_metadata_from_path(parm_path: &Path, must_exist: bool) -> Result<String, Error> {
+    // Given a path (file or directory) build metadata.
+    build_metadata_from_path_in_zone(parm_path, must_exist, None)
 }
 
 
-
-pub fn datetime_from_string(dt_as_string: &str) -> Option<DateTime<FixedOffset>> {
-    const FMT: &str = "%Y-%m-%dT%H%M%S%z";
-    match DateTime::parse_from_str(dt_as_string, FMT) {
-        #[allow(unused_variables)]
-        Err(e) => {
-            return None::<DateTime<FixedOffset>>;
-        }
-        Ok(f) => {
-            return Some(f);
-        }
-    };
- }
+pub fn build_metadata_from_path_in_zone(parm_path: &Path, must_exist: bool, zone: Option<Tz>) -> Result<String, Error> {
+    // Same as build_metadata_from_path(), but also re-expressing each datetime in a named time zone.
+    // Only a directory's own files are read.  Entries that cannot be read are skipped; for those warnings, and for
+    // subdirectories, use build_metadata_from_walk().
+    Ok(build_metadata_from_walk(parm_path, must_exist, zone, &WalkOptions::single_level())?.0)
+}
 
 
-pub fn build_metadata_from_path(parm_path: &Path, must_exist: bool) -> Result<String, String> {
-    // Given a path (file or directory) build metadata.
+pub fn build_metadata_from_walk(parm_path: &Path, must_exist: bool, zone: Option<Tz>, options: &WalkOptions)
+    -> Result<(String, Vec<Error>), Error> {
+    // Same as build_metadata_from_path_in_zone(), but directories are walked with 'options', in parallel.
+    // Also returns warnings: the entries that were skipped, because they could not be read or described.
     if must_exist && !parm_path.exists() {
-        let ret = format!("Path does not exist: '{}'", parm_path.to_str().unwrap());
-        return Err(ret)
+        return Err(Error::NotFound(parm_path.to_path_buf()));
     }
 
     // Scenario #1: parm_path is an existing Directory
     if parm_path.exists() && parm_path.is_dir() {
-        // Loop through the directory, and build a large JSON.
-
-        for entry in fs::read_dir(parm_path).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            println!("Found file: {:?}", path);
+        // Build a JSON array, with 1 object per stamped file.
+        let (mut data, warnings) = metadata::metadata_from_walk(walk::walk_directory(parm_path, options)?);
+        if let Some(zone) = zone {
+            data.iter_mut().for_each(|each| each.set_zone(zone));
         }
-        return Ok(String::from("Someday will return a JSON from a directory"));
+        return Ok((serde_json::to_string(&data)?, warnings));
     }
 
     // Scenario #2: Argument 'parm_path' represents a Single File or Name
+    Ok((serde_json::to_string(&metadata_from_name_in_zone(parm_path, zone)?)?, Vec::new()))
+}
+
+
+pub fn metadata_from_name_in_zone(parm_path: &Path, zone: Option<Tz>) -> Result<FileNameMeta, Error> {
+    // Metadata for a single file name.  The file does not need to exist.
     let dpathbuff: DatedPathBuf = DatedPathBuf::new_from_pathbuf(parm_path.to_path_buf())?;
-    match dpathbuff.json_metadata() {
-        Ok(value) => Ok(value.dump()),
-        Err(error) => Err(format!("Received a JSON encoding error: {:?}", error))
-    }            
+    Ok(match zone {
+        Some(zone) => dpathbuff.metadata_in_zone(zone),
+        None => dpathbuff.metadata()
+    })
 }
 
 
 pub fn is_path_file8601(path: &Path) -> bool {
-    // Checks if a Path contains a valid ISO 8601 datetime string.
-    if path.to_str().is_none() {
-        return false
-    }
-    let iso_regex = Regex::new(iso8601_pattern());
-    if iso_regex.is_err() {
-        return false
+    // Checks if a Path's file name contains a valid ISO 8601 datetime string.
+    // True exactly when split_stamped_bytes() would succeed; so detection and extraction always agree.
+    // Nothing is allocated: no Error for the (common) unstamped names, and no prefix or extensions for the stamped ones.
+    path.file_name().and_then(osbytes::os_str_bytes).is_some_and(is_stamped_bytes)
+}
+
+
+// The components of a file name that contains an ISO 8601 datetime.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StampedName<'a> {
+    pub prefix: Cow<'a, str>,  // Everything except the datetime and extensions, without the '_' separators.
+    pub datetime_string: &'a str,
+    pub datetime: DateTime<FixedOffset>,
+    pub extension: Option<Cow<'a, str>>  // One or more extensions, without a leading period.  Example: 'tar.gz'
+}
+
+// Same as StampedName, for file names that are not necessarily UTF-8.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StampedBytes<'a> {
+    pub prefix: Cow<'a, [u8]>,
+    pub datetime_string: &'a str,  // Always ASCII, so always UTF-8.
+    pub datetime: DateTime<FixedOffset>,
+    pub extension: Option<Cow<'a, [u8]>>
+}
+
+pub fn split_stamped_name(file_name: &str) -> Result<StampedName<'_>, Error> {
+    // Splits a stamped file name into prefix, datetime, and extension.
+    // The datetime is usually at the end of the stem.  But it can also be at the front, or in the middle:
+    //   'db_2020-03-04T170054-0800.tar.gz'       --> ('db', 2020-03-04T170054-0800, 'tar.gz')
+    //   '2020-03-04T170054-0800_db.tar.gz'       --> ('db', 2020-03-04T170054-0800, 'tar.gz')
+    //   'db_2020-03-04T170054-0800_full.tar.gz'  --> ('db_full', 2020-03-04T170054-0800, 'tar.gz')
+    let (_, _, format) = find_stamp(file_name).ok_or_else(|| Error::NoStamp(file_name.to_owned()))?;
+    split_stamped_name_with(file_name, format)
+}
+
+pub fn split_stamped_name_with(file_name: &str, format: StampFormat) -> Result<StampedName<'_>, Error> {
+    // Same as split_stamped_name(), but only accepts datetimes written in 1 StampFormat.
+    let stamped_bytes: StampedBytes = split_stamped_bytes_with(file_name.as_bytes(), format)?;
+    Ok(StampedName {
+        prefix: osbytes::cow_to_utf8(stamped_bytes.prefix)?,
+        datetime_string: stamped_bytes.datetime_string,
+        datetime: stamped_bytes.datetime,
+        extension: stamped_bytes.extension.map(osbytes::cow_to_utf8).transpose()?
+    })
+}
+
+pub fn split_stamped_bytes(file_name: &[u8]) -> Result<StampedBytes<'_>, Error> {
+    // Same as split_stamped_name(), for file names that are not necessarily UTF-8.
+    let (_, _, format) = find_stamp_bytes(file_name).ok_or_else(|| Error::NoStamp(osbytes::lossy(file_name)))?;
+    split_stamped_bytes_with(file_name, format)
+}
+
+pub fn split_stamped_bytes_with(file_name: &[u8], format: StampFormat) -> Result<StampedBytes<'_>, Error> {
+    // Same as split_stamped_bytes(), but only accepts datetimes written in 1 StampFormat.
+    let (start, end) = format.find_bytes(file_name).ok_or_else(|| Error::NoStamp(osbytes::lossy(file_name)))?;
+    let datetime_string: &str = std::str::from_utf8(&file_name[start..end])
+        .map_err(|_| Error::InvalidDateTime(osbytes::lossy(&file_name[start..end])))?;
+    let datetime: DateTime<FixedOffset> = format.parse(datetime_string)
+        .ok_or_else(|| Error::InvalidDateTime(datetime_string.to_owned()))?;
+
+    let before: &[u8] = strip_separator_suffix(&file_name[..start]);
+    let after: &[u8] = &file_name[end..];
+
+    // Nothing but the datetime and extensions.  Example: '2020-03-04T170054-0800.tar.gz'
+    if before.is_empty() && after.starts_with(b".") {
+        return Ok(StampedBytes {
+            prefix: Cow::Borrowed(b""),
+            datetime_string,
+            datetime,
+            extension: Some(Cow::Borrowed(&after[1..])).filter(|value| !value.is_empty())
+        });
     }
 
-    print_path_parts(path);
-    iso_regex.unwrap().is_match(path.to_str().unwrap())
+    // Remove the datetime and its separator, then split whatever remains into prefix and extensions.
+    // This way, datetimes placed between the extensions, or after them, are also handled:
+    //   'db.tar_2020-03-04T170054-0800.gz'   --> ('db', 2020-03-04T170054-0800, 'tar.gz')
+    //   'db.tar.gz_2020-03-04T170054-0800'   --> ('db', 2020-03-04T170054-0800, 'tar.gz')
+    let remainder: Cow<[u8]> = match strip_separator_prefix(after) {
+        Some((_, rest)) if before.is_empty() => Cow::Borrowed(rest),
+        Some((separator, rest)) if !rest.is_empty() => Cow::Owned([before, separator.as_bytes(), rest].concat()),
+        None if before.is_empty() => Cow::Borrowed(after),
+        None if !after.is_empty() => Cow::Owned([before, after].concat()),
+        _ => Cow::Borrowed(before)
+    };
+
+    let (prefix, extension): (Cow<[u8]>, Option<Cow<[u8]>>) = match remainder {
+        Cow::Borrowed(value) => {
+            let (stem, extension) = split_remainder(value);
+            (Cow::Borrowed(stem), extension.map(Cow::Borrowed))
+        },
+        Cow::Owned(value) => {
+            let (stem, extension) = split_remainder(&value);
+            (Cow::Owned(stem.to_owned()), extension.map(|value| Cow::Owned(value.to_owned())))
+        }
+    };
+
+    Ok(StampedBytes {
+        prefix,
+        datetime_string,
+        datetime,
+        extension
+    })
+}
+
+pub fn find_stamp(file_name: &str) -> Option<(usize, usize, StampFormat)> {
+    // Finds the datetime in a file name.  Returns its byte range, and the StampFormat it was written in.
+    find_stamp_bytes(file_name.as_bytes())
+}
+
+pub fn find_stamp_bytes(file_name: &[u8]) -> Option<(usize, usize, StampFormat)> {
+    // Same as find_stamp(), for file names that are not necessarily UTF-8.
+    // Any StampFormat is accepted.  The earliest datetime wins; and of those, the longest.
+    // A date alone is only a fallback, when there is no datetime anywhere in the name.  Otherwise
+    // '2019-01-01_db_2020-03-04T170054-0800.sql' would be stamped with the wrong date.
+    StampFormat::ALL.iter()
+        .filter(|format| **format != StampFormat::DateOnly)
+        .filter_map(|format| format.find_bytes(file_name).map(|(start, end)| (start, Reverse(end), *format)))
+        .min_by_key(|(start, end, _)| (*start, *end))
+        .map(|(start, Reverse(end), format)| (start, end, format))
+        .or_else(|| StampFormat::DateOnly.find_bytes(file_name).map(|(start, end)| (start, end, StampFormat::DateOnly)))
 }
 
 // ---- PRIVATE FUNCTIONS -----
 
-fn iso8601_pattern() -> &'static str {
-    // Return a regular expression for matcing ISO 8601 datetime format.
-    // Using the 'concat' macro so I can document the components of the expression.
-    concat!(
-         "([0-9]{4})"           // year
-        ,"-?(1[0-2]|0[1-9])"    // month
-        ,"-?(3[01]|0[1-9]|[12][0-9])" // day
-        ,"T" // date-time seperator
-        ,"(2[0-3]|[01][0-9])"  // hour
-        ,":?([0-5][0-9])"  // minute
-        ,":?([0-5][0-9])"  // second
-        ,"-?(2[0-3]|[01][0-9])" // offset hour
-        ,":?([0-5][0-9])"  // offset minute
-    )
+fn is_stamped_bytes(file_name: &[u8]) -> bool {
+    // The same checks as split_stamped_bytes(), in the same order; but only the answer is kept.
+    find_stamp_bytes(file_name).is_some_and(|(start, end, format)| {
+        std::str::from_utf8(&file_name[start..end]).ok().and_then(|value| format.parse(value)).is_some()
+    })
 }
 
+fn strip_separator_suffix(value: &[u8]) -> &[u8] {
+    // Removes whichever known separator sits just before a datetime.
+    StampSeparator::ALL.iter()
+        .find_map(|separator| value.strip_suffix(separator.as_str().as_bytes()))
+        .unwrap_or(value)
+}
 
-pub fn parse_filename_parts(filename: &str) -> (&str, Option<&str>) {
+fn strip_separator_prefix(value: &[u8]) -> Option<(&'static str, &[u8])> {
+    // Removes whichever known separator sits just after a datetime.
+    // A period is never a separator here: after a datetime, it begins the extensions.
+    StampSeparator::ALL.iter()
+        .filter(|separator| **separator != StampSeparator::Period)
+        .find_map(|separator| value.strip_prefix(separator.as_str().as_bytes()).map(|rest| (separator.as_str(), rest)))
+}
+
+fn split_remainder(remainder: &[u8]) -> (&[u8], Option<&[u8]>) {
+    // Same as parse_filename_bytes(), but a lone period (or two) is just a (strange) stem.
+    parse_filename_bytes(remainder).unwrap_or((remainder, None))
+}
+
+
+pub fn parse_filename_parts(filename: &str) -> Result<(&str, Option<&str>), Error> {
     // Accept and return references.  No ownership changes.  No Heap strings.
     // Returns 'stem' and 'extension'
+    // The work is done by parse_filename_bytes().  A period is a single byte, so the slices fall on character boundaries.
+    let (stem, extension) = parse_filename_bytes(filename.as_bytes())?;
+    Ok((&filename[..stem.len()], extension.map(|value| &filename[filename.len() - value.len()..])))
+}
+
+pub fn parse_filename_bytes(filename: &[u8]) -> Result<(&[u8], Option<&[u8]>), Error> {
+    // Same as parse_filename_parts(), for file names that are not necessarily UTF-8.
     // This is where we handle multiple extensions like '.tar.gz'
     // UTF-8 for a '.' (period, dot, full stop) is 2E
     
-    if filename == "." {
-        panic!("Invalid file name: single Unicode character U+002E (period, full stop)");
+    // A single Unicode character U+002E (period, full stop) is the current directory, and two are its parent.
+    // Neither is a file name, just as Path::file_name() says for them.
+    if filename == b"." || filename == b".." {
+        return Err(Error::InvalidFileName(osbytes::lossy(filename)));
     }
 
     // Detect a leading period (Unix hidden filename)
     let mut index_start = 0;
-    if filename.starts_with('.') {
+    if filename.starts_with(b".") {
         index_start = 1;
     }
 
     // Example of components:  ['some_file_name', 'tar', 'gz']
-    let components: Vec<&str> = filename[index_start..].split('.').collect();
+    let components: Vec<&[u8]> = filename[index_start..].split(|byte| *byte == b'.').collect();
 
     // Scenario 1: Only single component, so there are no extensions.
     if components.len() == 1 && components[0] == &filename[index_start..] {
-        return (filename, None)
+        return Ok((filename, None))
     }
 
     // Scenario 2: There exists at least 1 potential extension.
@@ -199,24 +316,22 @@ pub fn parse_filename_parts(filename: &str) -> (&str, Option<&str>) {
         }
 
         if is_extension_valid(component) {
-            // println!("Stem: {}", &filename[0..stop_index]);
-            // println!("Extension: {:?}",  Some(&filename[stop_index+1..]));
-            return (&filename[0..stop_index], Some(&filename[stop_index+1..]));
+            return Ok((&filename[0..stop_index], Some(&filename[stop_index+1..])));
         }
-        stop_index += component.len();
+        stop_index += component.len() + 1;  // include the period that preceded this component
     };
 
-    (filename, None)
+    Ok((filename, None))
 }
 
 
-fn is_extension_valid(extension: &str) -> bool {
+fn is_extension_valid(extension: &[u8]) -> bool {
     // Decide whether a string is also a valid Filename extension.
-    // For now, we are only rejecting extensions that begin with 0..9
-    let first_char: &char= &extension.chars().next().unwrap();
-
-    // Is first character a base 10 digit?
-    !first_char.is_ascii_digit()
+    // For now, we are only rejecting extensions that are empty, or begin with 0..9
+    match extension.first() {
+        Some(first_byte) => !first_byte.is_ascii_digit(),  // Is first character a base 10 digit?
+        None => false
+    }
 }
 
 // ----UNIT TESTS----
@@ -246,7 +361,54 @@ fn filename_components() {
                                 Some("tar.gz") ];
 
     for (i, x) in filenames.iter().enumerate() {
-        assert_eq!( stem_expected[i], parse_filename_parts(&x).0 );
-        assert_eq!( extension_expected[i], parse_filename_parts(&x).1 );
+        assert_eq!( stem_expected[i], parse_filename_parts(x).unwrap().0 );
+        assert_eq!( extension_expected[i], parse_filename_parts(x).unwrap().1 );
+    }
+}
+
+#[test]
+fn stamped_name_components() {
+    let filenames = ["db_2020-03-04T170054-0800.tar.gz",
+                     "db_2020-03-04T170054-0800",
+                     "2020-03-04T170054-0800_db.tar.gz",
+                     "db_2020-03-04T170054-0800_full.tar.gz",
+                     "db_v1.7.5_2020-03-04T170054-0800.sql",
+                     "db.tar_2020-03-04T170054-0800.gz",
+                     "db.tar.gz_2020-03-04T170054-0800",
+                     "2020-03-04T170054-0800.tar.gz" ];
+
+    let expected = [ ("db", Some("tar.gz")),
+                     ("db", None),
+                     ("db", Some("tar.gz")),
+                     ("db_full", Some("tar.gz")),
+                     ("db_v1.7.5", Some("sql")),
+                     ("db", Some("tar.gz")),
+                     ("db", Some("tar.gz")),
+                     ("", Some("tar.gz")) ];
+
+    for (i, x) in filenames.iter().enumerate() {
+        let stamped_name: StampedName = split_stamped_name(x).unwrap();
+        assert_eq!( expected[i].0, stamped_name.prefix );
+        assert_eq!( "2020-03-04T170054-0800", stamped_name.datetime_string );
+        assert_eq!( expected[i].1, stamped_name.extension.as_deref() );
+    }
+    assert!( matches!(split_stamped_name("db_2020-13-04T170054-0800.tar.gz"), Err(Error::NoStamp(_))) );
+
+    // Other profiles are recognized too.
+    assert_eq!( split_stamped_name("db_20200304T170054-0800.tar.gz").unwrap().datetime_string, "20200304T170054-0800" );
+    assert_eq!( split_stamped_name("db_2020-03-04.tar.gz").unwrap().datetime_string, "2020-03-04" );
+    assert_eq!( split_stamped_name("db_2020-03-04T170054.123-0800.tar.gz").unwrap().prefix, "db" );
+
+    // A date alone only counts when there is no full datetime; and never when it is the start of one.
+    let stamped_name: StampedName = split_stamped_name("2019-01-01_db_2020-03-04T170054-0800.sql").unwrap();
+    assert_eq!( (stamped_name.prefix.as_ref(), stamped_name.datetime_string), ("2019-01-01_db", "2020-03-04T170054-0800") );
+    assert!( matches!(split_stamped_name("dump_2024-01-02T030405,5Z.sql"), Err(Error::NoStamp(_))) );
+
+    // Detection agrees with extraction.
+    for (file_name, expected) in [("db_2020-03-04.sql", true), ("db_2020-03-04T1700-0800.sql", true),
+                                  ("db_2020-0304T17:0054-0800.sql", false), ("db_2020-02-30T170054-0800.sql", false),
+                                  ("dump_2024-01-02T030405,5Z.sql", false), ("2020-03-04/notes.txt", false)] {
+        assert_eq!( is_path_file8601(Path::new(file_name)), expected, "{}", file_name );
+        assert_eq!( split_stamped_name(Path::new(file_name).file_name().unwrap().to_str().unwrap()).is_ok(), expected );
     }
 }
@@ -0,0 +1,238 @@
+// metadata.rs
+// This module is about the metadata we can learn from a file name that contains an ISO 8601 datetime.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// 3rd Party
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{is_path_file8601, split_stamped_bytes, Error, StampedBytes};
+use super::osbytes;
+use super::walk::{walk_directory, Walk, WalkOptions};
+use super::dates::fractional_digits;
+
+
+// Files with the same key are versions of 1 backup: (directory, prefix, extensions).  See DatedPathBuf::group_key().
+pub type GroupKey<'a> = (Option<&'a Path>, &'a [u8], Option<&'a [u8]>);
+
+
+// Same as PathBuf, but must contain an ISO 8601 datetime in the file name.
+// The file name does not need to be UTF-8.  Its prefix and extensions are kept as bytes, so that 2 names which differ
+// only in their invalid bytes are never mistaken for the same backup; they are only made lossy for display.
+pub struct DatedPathBuf {
+    pathbuf: PathBuf,
+    prefix: Vec<u8>,
+    datetime_string: String,
+    extension: Option<Vec<u8>>,
+    datetime: DateTime<FixedOffset>
+}
+
+impl DatedPathBuf {
+
+    pub fn new_from_pathbuf(path_buffer: PathBuf) -> Result<DatedPathBuf, Error> {
+        // The datetime must be in the file name itself, not just somewhere in the parent directories.
+        let stamped_bytes: StampedBytes = split_stamped_bytes(osbytes::file_name_bytes(&path_buffer)?)?;
+
+        Ok(DatedPathBuf {
+            prefix: stamped_bytes.prefix.into_owned(),
+            datetime_string: stamped_bytes.datetime_string.to_owned(),
+            extension: stamped_bytes.extension.map(Cow::into_owned),
+            datetime: stamped_bytes.datetime,
+            pathbuf: path_buffer
+        })
+    }
+
+    pub fn as_path(&self) -> &Path {
+        self.pathbuf.as_path()
+    }
+
+    pub fn file_name(&self) -> Cow<'_, str> {
+        // Invalid UTF-8 is replaced with U+FFFD; a UTF-8 name is borrowed as is.
+        self.pathbuf.file_name().map(|value| value.to_string_lossy()).unwrap_or_default()
+    }
+
+    pub fn prefix(&self) -> Cow<'_, str> {
+        // The file name without the datetime, its '_' separator, and extensions.  Lossy, like file_name().
+        String::from_utf8_lossy(&self.prefix)
+    }
+
+    pub fn datetime_string(&self) -> &str {
+        &self.datetime_string
+    }
+
+    pub fn extension(&self) -> Option<Cow<'_, str>> {
+        self.extension.as_deref().map(String::from_utf8_lossy)
+    }
+
+    pub fn suffix(&self) -> String {
+        // The extensions, with a leading period (.tar.gz)  Empty when there are no extensions.
+        match self.extension() {
+            Some(value) => format!(".{}", value),
+            None => String::new()
+        }
+    }
+
+    pub fn datetime(&self) -> DateTime<FixedOffset> {
+        self.datetime
+    }
+
+    pub fn group_key(&self) -> GroupKey<'_> {
+        // Files with the same key are versions of 1 backup: the same directory, prefix, and extensions.
+        // So a dump (db_<datetime>.sql) and its checksum (db_<datetime>.sql.sha256) are separate groups.
+        // Compared as bytes, not as the lossy strings.
+        (self.pathbuf.parent(), &self.prefix, self.extension.as_deref())
+    }
+
+    pub fn utc_datetime(&self) -> DateTime<Utc> {
+        self.datetime.with_timezone(&Utc)
+    }
+
+    pub fn metadata(&self) -> FileNameMeta {
+        let utc_datetime: DateTime<Utc> = self.utc_datetime();
+        FileNameMeta {
+            path: self.pathbuf.clone(),
+            parentdir: self.pathbuf.parent().map(Path::to_path_buf).unwrap_or_default(),
+            full_name: self.file_name().into_owned(),
+            prefix: self.prefix().into_owned(),
+            datetime_string: self.datetime_string().to_owned(),
+            suffix: self.suffix(),
+            fractional_digits: fractional_digits(self.datetime_string()),
+            orig_datetime: self.datetime,
+            utc_datetime,
+            utc_date: utc_datetime.naive_utc().date(),
+            utc_time: utc_datetime.time(),
+            zone: None,
+            zone_datetime: None,
+            zone_date: None,
+            zone_time: None,
+            sort_code_newest: None,
+            sort_code_oldest: None
+        }
+    }
+
+    pub fn metadata_in_zone(&self, zone: Tz) -> FileNameMeta {
+        // Same as metadata(), plus the datetime, date, and time re-expressed in a named time zone.
+        let mut metadata: FileNameMeta = self.metadata();
+        metadata.set_zone(zone);
+        metadata
+    }
+}
+
+
+// Contains useful metadata about a stamped file.  Same keys as the Python edition of 'filedate'.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileNameMeta {
+    #[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+    pub path: PathBuf,
+    #[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+    pub parentdir: PathBuf,
+    pub full_name: String,
+    pub prefix: String,  // The part before the ISO 8601 DateTime
+    pub datetime_string: String,
+    pub suffix: String,  // One or more file extensions (.tar, .gz, .zip, .sh)
+    // The precision of the datetime string's fractional seconds; 3 for milliseconds.  Zero when there are none.
+    #[serde(default)]
+    pub fractional_digits: usize,
+    pub orig_datetime: DateTime<FixedOffset>,
+    pub utc_datetime: DateTime<Utc>,
+    pub utc_date: NaiveDate,
+    pub utc_time: NaiveTime,
+    // The 'zone' elements only exist when metadata was requested for a named time zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone_datetime: Option<DateTime<FixedOffset>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone_time: Option<NaiveTime>,
+    // Sort Codes only exist when metadata is built for a directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_code_newest: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_code_oldest: Option<usize>
+}
+
+impl FileNameMeta {
+    pub fn set_zone(&mut self, zone: Tz) {
+        // Re-express the file's datetime in a named time zone.  For example, the team's 'utc_date' equivalent.
+        let zoned: DateTime<Tz> = self.utc_datetime.with_timezone(&zone);
+        self.zone = Some(zone.name().to_owned());
+        self.zone_datetime = Some(zoned.with_timezone(&zoned.offset().fix()));
+        self.zone_date = Some(zoned.date_naive());
+        self.zone_time = Some(zoned.time());
+    }
+}
+
+
+pub fn files_in_directory(directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    // Returns the paths of every file in a directory, ordered by path.  Subdirectories are skipped.
+    // So are entries that cannot be read; for those warnings, use walk_directory().
+    Ok(walk_directory(directory, &WalkOptions::single_level())?.files)
+}
+
+
+pub fn metadata_from_directory(directory: &Path) -> Result<(Vec<FileNameMeta>, Vec<Error>), Error> {
+    // Returns metadata for every stamped file in a directory, ordered by path; and warnings, like metadata_from_walk().
+    // Subdirectories, and files without an ISO 8601 datetime, are skipped.  Names are parsed in parallel.
+    Ok(metadata_from_walk(walk_directory(directory, &WalkOptions::single_level())?))
+}
+
+
+pub fn metadata_from_walk(walk: Walk) -> (Vec<FileNameMeta>, Vec<Error>) {
+    // Same as metadata_from_paths(), for the files of a walk.  Also returns the walk's warnings.
+    (metadata_from_paths(walk.files), walk.warnings)
+}
+
+
+pub fn dated_paths(paths: Vec<PathBuf>) -> Vec<DatedPathBuf> {
+    // Keeps the stamped paths, in the same order.  Paths without an ISO 8601 datetime are skipped.
+    // The names are parsed in parallel, on Rayon's current thread pool.  Unstamped names are skipped before parsing,
+    // so no NoStamp Error is built (and thrown away) for each of them.
+    paths.into_par_iter()
+        .filter(|path| is_path_file8601(path))
+        .filter_map(|path| DatedPathBuf::new_from_pathbuf(path).ok())
+        .collect()
+}
+
+
+pub fn metadata_from_paths(paths: Vec<PathBuf>) -> Vec<FileNameMeta> {
+    // Returns metadata for every stamped path, in the same order.  See dated_paths().
+    // Each element also receives Sort Codes, for knowing its position relative to files with the same prefix and extensions.
+    let dated_paths: Vec<DatedPathBuf> = dated_paths(paths);
+
+    let mut data: Vec<FileNameMeta> = Vec::with_capacity(dated_paths.len());
+    for (dated_path, (newest, oldest)) in dated_paths.iter().zip(sort_codes(&dated_paths)) {
+        let mut metadata: FileNameMeta = dated_path.metadata();
+        metadata.sort_code_newest = Some(newest);
+        metadata.sort_code_oldest = Some(oldest);
+        data.push(metadata);
+    }
+    data
+}
+
+
+fn sort_codes(dated_paths: &[DatedPathBuf]) -> Vec<(usize, usize)> {
+    // Returns each path's position within its group, based on datetime.  See DatedPathBuf::group_key().
+    // The tuple is (newest first, oldest first).  So the newest file in a group is (0, N-1).
+    let mut groups: HashMap<GroupKey, Vec<usize>> = HashMap::new();
+    for (index, dated_path) in dated_paths.iter().enumerate() {
+        groups.entry(dated_path.group_key()).or_default().push(index);
+    }
+
+    let mut codes: Vec<(usize, usize)> = vec![(0, 0); dated_paths.len()];
+    for indices in groups.values_mut() {
+        // Oldest first.  The sort is stable, so identical datetimes keep their path order.
+        indices.sort_by_key(|index| dated_paths[*index].utc_datetime());
+        let count: usize = indices.len();
+        for (position, index) in indices.iter().enumerate() {
+            codes[*index] = (count - 1 - position, position);
+        }
+    }
+    codes
+}
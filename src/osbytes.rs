@@ -0,0 +1,79 @@
+// osbytes.rs
+// This module is about file names as bytes.  On Unix, a file name is any sequence of bytes; not necessarily UTF-8.
+// Stamping and detection work on those bytes, so that legacy names (such as Latin-1) round-trip losslessly.
+// On other platforms, file names must be valid UTF-8.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+// 3rd Party
+use serde::Serializer;
+
+use super::Error;
+
+
+#[cfg(unix)]
+pub fn os_str_bytes(value: &OsStr) -> Option<&[u8]> {
+    Some(value.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub fn os_str_bytes(value: &OsStr) -> Option<&[u8]> {
+    value.to_str().map(str::as_bytes)
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    Some(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    String::from_utf8(bytes).ok().map(OsString::from)
+}
+
+
+pub fn file_name_bytes(path: &Path) -> Result<&[u8], Error> {
+    // The last component of a Path, as bytes.
+    let file_name: &OsStr = path.file_name().ok_or_else(|| Error::InvalidFileName(path.display().to_string()))?;
+    os_str_bytes(file_name).ok_or_else(|| Error::NotUtf8(path.to_path_buf()))
+}
+
+pub fn with_file_name_bytes(path: &Path, file_name: Vec<u8>) -> Result<PathBuf, Error> {
+    // Same as Path::with_file_name(), but the new file name is bytes.
+    let file_name: OsString = os_string_from_bytes(file_name).ok_or_else(|| Error::NotUtf8(path.to_path_buf()))?;
+    Ok(path.with_file_name(file_name))
+}
+
+pub fn cow_to_utf8(value: Cow<[u8]>) -> Result<Cow<str>, Error> {
+    // The bytes were cut from a string slice, at ASCII positions.  So this only fails if that promise is broken.
+    match value {
+        Cow::Borrowed(bytes) => std::str::from_utf8(bytes).map(Cow::Borrowed)
+            .map_err(|_| Error::InvalidFileName(lossy(bytes))),
+        Cow::Owned(bytes) => into_utf8(bytes).map(Cow::Owned)
+    }
+}
+
+pub fn into_utf8(bytes: Vec<u8>) -> Result<String, Error> {
+    // Same as cow_to_utf8(), for owned bytes.
+    String::from_utf8(bytes).map_err(|error| Error::InvalidFileName(lossy(error.as_bytes())))
+}
+
+pub fn lossy(bytes: &[u8]) -> String {
+    // For error messages.
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+pub fn serialize_lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    // For serde's 'serialize_with'.  A Path that is not UTF-8 has U+FFFD in place of the invalid bytes; instead of
+    // failing halfway through a list.
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+pub fn serialize_all_lossy<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+    // Same as serialize_lossy(), for a list of paths.
+    serializer.collect_seq(paths.iter().map(|path| path.to_string_lossy()))
+}
@@ -0,0 +1,270 @@
+// output.rs
+// This module is about writing lists of results (metadata, retention plans, verifications, touches) for other programs.
+//
+//   json:    1 JSON array.  For 'jq'.
+//   ndjson:  1 JSON object per line.  For streaming, and 'jq -c'.
+//   csv:     RFC 4180, with a header row.  For spreadsheets.
+//   tsv:     Tab-separated, with a header row.  Tabs, newlines and backslashes inside values are escaped (\t, \n, \\).
+//   table:   Columns aligned with spaces, with an upper case header row.  For humans.
+
+use std::io::Write;
+use std::path::Path;
+
+// 3rd Party
+use serde::Serialize;
+
+use super::metadata::FileNameMeta;
+use super::retention::PlanEntry;
+use super::stamp::Touched;
+use super::verify::Verification;
+use super::Error;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    NdJson,
+    Csv,
+    Tsv,
+    Table
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 5] = [
+        OutputFormat::Json, OutputFormat::NdJson, OutputFormat::Csv, OutputFormat::Tsv, OutputFormat::Table
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::NdJson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Table => "table"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        OutputFormat::ALL.iter().find(|format| format.name() == name).copied()
+    }
+}
+
+
+// Something that can be written as 1 row of a CSV, TSV, or table.  JSON formats use its Serialize instead.
+pub trait Record: Serialize {
+    const COLUMNS: &'static [&'static str];
+
+    // 1 value per column, in the same order.  Missing values are empty strings.
+    fn values(&self) -> Vec<String>;
+}
+
+
+pub fn write_records<R: Record, W: Write>(writer: &mut W, format: OutputFormat, records: &[R]) -> Result<(), Error> {
+    // Writes every record in the chosen format.  Each format ends with a newline.
+    // JSON is serialized completely before anything is written; so a failure never leaves half an array behind.
+    match format {
+        OutputFormat::Json => {
+            let mut buffer: Vec<u8> = serde_json::to_vec(records)?;
+            buffer.push(b'\n');
+            writer.write_all(&buffer).map_err(write_error)
+        },
+        OutputFormat::NdJson => {
+            let mut buffer: Vec<u8> = Vec::new();
+            for record in records {
+                serde_json::to_writer(&mut buffer, record)?;
+                buffer.push(b'\n');
+            }
+            writer.write_all(&buffer).map_err(write_error)
+        },
+        OutputFormat::Csv => write_delimited(writer, R::COLUMNS, records, ",", csv_escape),
+        OutputFormat::Tsv => write_delimited(writer, R::COLUMNS, records, "\t", tsv_escape),
+        OutputFormat::Table => write_table(writer, R::COLUMNS, records)
+    }
+}
+
+
+impl Record for FileNameMeta {
+    const COLUMNS: &'static [&'static str] = &[
+        "path", "parentdir", "full_name", "prefix", "datetime_string", "suffix", "fractional_digits",
+        "orig_datetime", "utc_datetime", "utc_date", "utc_time", "zone", "zone_datetime", "zone_date", "zone_time",
+        "sort_code_newest", "sort_code_oldest"
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.path.display().to_string(),
+            self.parentdir.display().to_string(),
+            self.full_name.clone(),
+            self.prefix.clone(),
+            self.datetime_string.clone(),
+            self.suffix.clone(),
+            self.fractional_digits.to_string(),
+            self.orig_datetime.to_rfc3339(),
+            self.utc_datetime.to_rfc3339(),
+            self.utc_date.to_string(),
+            self.utc_time.to_string(),
+            optional(&self.zone),
+            optional(&self.zone_datetime.map(|value| value.to_rfc3339())),
+            optional(&self.zone_date),
+            optional(&self.zone_time),
+            optional(&self.sort_code_newest),
+            optional(&self.sort_code_oldest)
+        ]
+    }
+}
+
+impl Record for PlanEntry {
+    const COLUMNS: &'static [&'static str] = &["action", "path", "reasons"];
+
+    fn values(&self) -> Vec<String> {
+        let reasons: Vec<String> = self.reasons.iter().map(|reason| reason.to_string()).collect();
+        vec![self.action.to_string(), self.path.display().to_string(), reasons.join(",")]
+    }
+}
+
+impl Record for Verification {
+    const COLUMNS: &'static [&'static str] = &["verdict", "delta", "stamp", "mtime", "path"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.verdict.to_string(),
+            self.delta_string(),
+            self.stamp.to_rfc3339(),
+            self.mtime.to_rfc3339(),
+            self.path.display().to_string()
+        ]
+    }
+}
+
+impl Record for Touched {
+    const COLUMNS: &'static [&'static str] = &["path", "stamp", "changed"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.path.display().to_string(),
+            self.stamp.to_rfc3339(),
+            self.changed.to_string()
+        ]
+    }
+}
+
+
+// ---- PRIVATE FUNCTIONS -----
+
+fn write_error(error: std::io::Error) -> Error {
+    // The destination is not a file we know the name of; usually stdout.
+    Error::from_io(Path::new("-"), error)
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(ToString::to_string).unwrap_or_default()
+}
+
+fn write_delimited<R: Record, W: Write>(writer: &mut W, columns: &[&str], records: &[R],
+                                        delimiter: &str, escape: fn(&str) -> String) -> Result<(), Error> {
+    // A header row, then 1 row per record.
+    let header: Vec<String> = columns.iter().map(|column| escape(column)).collect();
+    writeln!(writer, "{}", header.join(delimiter)).map_err(write_error)?;
+    for record in records {
+        let row: Vec<String> = record.values().iter().map(|value| escape(value)).collect();
+        writeln!(writer, "{}", row.join(delimiter)).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn csv_escape(value: &str) -> String {
+    // Values containing a comma, quote, or line break are quoted; and their quotes doubled.
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+    else {
+        value.to_owned()
+    }
+}
+
+fn tsv_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn write_table<R: Record, W: Write>(writer: &mut W, columns: &[&str], records: &[R]) -> Result<(), Error> {
+    // Every column is padded to its widest value, except the last.  Rows never end with spaces.
+    // Line breaks inside values would break the alignment, so they are escaped like TSV.
+    let header: Vec<String> = columns.iter().map(|column| column.to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = records.iter()
+        .map(|record| record.values().iter().map(|value| tsv_escape(value)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let last: usize = row.len().saturating_sub(1);
+        let cells: Vec<String> = row.iter().enumerate()
+            .map(|(index, value)| if index == last { value.clone() } else { format!("{:<width$}", value, width = widths[index]) })
+            .collect();
+        writeln!(writer, "{}", cells.join("  ").trim_end()).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+
+// ----UNIT TESTS----
+
+#[cfg(test)]
+fn written(format: OutputFormat, records: &[PlanEntry]) -> String {
+    let mut buffer: Vec<u8> = Vec::new();
+    write_records(&mut buffer, format, records).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn output_formats() {
+    use std::path::PathBuf;
+    use super::retention::{Action, KeepReason};
+
+    let records = [
+        PlanEntry { path: PathBuf::from("db, \"full\"_2020-03-01T000000+0000.sql"), action: Action::Keep,
+                    reasons: vec![KeepReason::Daily, KeepReason::Monthly] },
+        PlanEntry { path: PathBuf::from("db\tx_2020-02-01T000000+0000.sql"), action: Action::Delete, reasons: vec![] }
+    ];
+
+    assert_eq!(written(OutputFormat::Json, &records[1..]),
+               "[{\"path\":\"db\\tx_2020-02-01T000000+0000.sql\",\"action\":\"delete\",\"reasons\":[]}]\n");
+    assert_eq!(written(OutputFormat::NdJson, &records).lines().count(), 2);
+    assert_eq!(written(OutputFormat::Csv, &records),
+               "action,path,reasons\n\
+                keep,\"db, \"\"full\"\"_2020-03-01T000000+0000.sql\",\"daily,monthly\"\n\
+                delete,db\tx_2020-02-01T000000+0000.sql,\n");
+    assert_eq!(written(OutputFormat::Tsv, &records[1..]),
+               "action\tpath\treasons\ndelete\tdb\\tx_2020-02-01T000000+0000.sql\t\n");
+    assert_eq!(written(OutputFormat::Table, &records),
+               "ACTION  PATH                                   REASONS\n\
+                keep    db, \"full\"_2020-03-01T000000+0000.sql  daily,monthly\n\
+                delete  db\\tx_2020-02-01T000000+0000.sql\n");
+    assert_eq!(OutputFormat::from_name("ndjson"), Some(OutputFormat::NdJson));
+}
+
+#[cfg(unix)]
+#[test]
+fn output_non_utf8_paths() {
+    // A Latin-1 path is written with U+FFFD, rather than stopping halfway through the output.
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use super::retention::{Action, RetentionPlan};
+
+    let path = PathBuf::from(OsStr::from_bytes(b"caf\xe9/db_2020-03-01T000000+0000.sql"));
+    let records = [PlanEntry { path: path.clone(), action: Action::Delete, reasons: vec![] }];
+    assert_eq!(written(OutputFormat::Json, &records),
+               "[{\"path\":\"caf\u{FFFD}/db_2020-03-01T000000+0000.sql\",\"action\":\"delete\",\"reasons\":[]}]\n");
+    assert!(written(OutputFormat::NdJson, &records).starts_with("{\"path\":\"caf\u{FFFD}/"));
+
+    let plan = RetentionPlan { keep: vec![], delete: vec![path] };
+    assert_eq!(serde_json::to_string(&plan).unwrap(),
+               "{\"keep\":[],\"delete\":[\"caf\u{FFFD}/db_2020-03-01T000000+0000.sql\"]}");
+}
@@ -0,0 +1,262 @@
+// retention.rs
+// This module is about Backup File Rotation: given some stamped files, which should be kept, and which deleted?
+//
+// The policies work the same way as 'borg prune' and 'restic forget':
+//   keep_last:     The N newest files.
+//   keep_daily:    The newest file of each day, for the N most recent days that have files.
+//   keep_weekly:   The newest file of each ISO week, for the N most recent weeks that have files.
+//   keep_monthly:  The newest file of each month, for the N most recent months that have files.
+//   keep_yearly:   The newest file of each year, for the N most recent years that have files.
+// Counting periods that *have files* (rather than calendar periods) means a backup job that stopped running
+// will never have its last remaining files deleted.
+//
+// Every policy is applied separately to each group of files sharing a directory, prefix, and extensions.  So a
+// checksum or signature beside each dump never takes the dump's place.
+// Periods are based on the UTC datetime in each file name.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+// 3rd Party
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::metadata::{dated_paths, DatedPathBuf, GroupKey};
+
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_monthly: usize,
+    pub keep_yearly: usize
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        // An empty policy keeps nothing at all.
+        *self == RetentionPolicy::default()
+    }
+}
+
+
+// Why a file was kept.  A file can be kept for more than 1 reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepReason {
+    Last,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly
+}
+
+impl fmt::Display for KeepReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name: &str = match self {
+            KeepReason::Last => "last",
+            KeepReason::Daily => "daily",
+            KeepReason::Weekly => "weekly",
+            KeepReason::Monthly => "monthly",
+            KeepReason::Yearly => "yearly"
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeptFile {
+    #[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+    pub path: PathBuf,
+    pub reasons: Vec<KeepReason>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPlan {
+    pub keep: Vec<KeptFile>,
+    #[serde(serialize_with = "crate::osbytes::serialize_all_lossy")]
+    pub delete: Vec<PathBuf>
+}
+
+impl RetentionPlan {
+    pub fn entries(&self) -> Vec<PlanEntry> {
+        // The files to keep and to delete, as 1 list ordered by path.
+        let mut entries: Vec<PlanEntry> = self.keep.iter()
+            .map(|kept| PlanEntry { path: kept.path.clone(), action: Action::Keep, reasons: kept.reasons.clone() })
+            .chain(self.delete.iter().map(|path| PlanEntry { path: path.clone(), action: Action::Delete, reasons: Vec::new() }))
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Keep,
+    Delete
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Action::Keep => "keep",
+            Action::Delete => "delete"
+        })
+    }
+}
+
+// 1 file of a RetentionPlan.  Files to delete have no reasons.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanEntry {
+    #[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+    pub path: PathBuf,
+    pub action: Action,
+    pub reasons: Vec<KeepReason>
+}
+
+
+pub fn apply_policy(policy: &RetentionPolicy, paths: &[PathBuf]) -> RetentionPlan {
+    // Decides which paths to keep, and which to delete.  Both are ordered by path.
+    // Paths without an ISO 8601 datetime in their file name are in neither; they are not ours to judge.
+    // Neither are stamped files whose names are not UTF-8.  To warn about those, use apply_policy_to_dated().
+    apply_policy_to_dated(policy, &dated_paths(paths.to_vec()))
+}
+
+pub fn apply_policy_to_dated(policy: &RetentionPolicy, dated_paths: &[DatedPathBuf]) -> RetentionPlan {
+    // Same as apply_policy(), for paths already parsed by metadata::dated_paths().
+    let mut groups: HashMap<GroupKey, Vec<&DatedPathBuf>> = HashMap::new();
+    for dated_path in dated_paths {
+        groups.entry(dated_path.group_key()).or_default().push(dated_path);
+    }
+
+    let mut plan = RetentionPlan::default();
+    for group in groups.values_mut() {
+        // Newest first.  Ties are broken by path, so the outcome never depends on input order.
+        // The same file given twice (such as from overlapping directories) must not be both kept and deleted.
+        group.sort_by(|a, b| b.utc_datetime().cmp(&a.utc_datetime()).then_with(|| b.as_path().cmp(a.as_path())));
+        group.dedup_by(|a, b| a.as_path() == b.as_path());
+
+        let mut reasons: Vec<Vec<KeepReason>> = vec![Vec::new(); group.len()];
+        for file_reasons in reasons.iter_mut().take(policy.keep_last) {
+            file_reasons.push(KeepReason::Last);
+        }
+        keep_per_period(group, policy.keep_daily, KeepReason::Daily, &mut reasons);
+        keep_per_period(group, policy.keep_weekly, KeepReason::Weekly, &mut reasons);
+        keep_per_period(group, policy.keep_monthly, KeepReason::Monthly, &mut reasons);
+        keep_per_period(group, policy.keep_yearly, KeepReason::Yearly, &mut reasons);
+
+        for (dated_path, reasons) in group.iter().zip(reasons) {
+            if reasons.is_empty() {
+                plan.delete.push(dated_path.as_path().to_path_buf());
+            }
+            else {
+                plan.keep.push(KeptFile { path: dated_path.as_path().to_path_buf(), reasons });
+            }
+        }
+    }
+
+    plan.keep.sort_by(|a, b| a.path.cmp(&b.path));
+    plan.delete.sort();
+    plan
+}
+
+// ---- PRIVATE FUNCTIONS -----
+
+fn keep_per_period(group: &[&DatedPathBuf], count: usize, reason: KeepReason, reasons: &mut [Vec<KeepReason>]) {
+    // Keeps the newest file in each of the 'count' most recent periods.  The group must be sorted newest first.
+    let mut last_period: Option<(i32, u32)> = None;
+    let mut kept: usize = 0;
+    for (index, dated_path) in group.iter().enumerate() {
+        if kept == count {
+            break;
+        }
+        let period: (i32, u32) = period_of(&dated_path.utc_datetime(), reason);
+        if last_period != Some(period) {
+            reasons[index].push(reason);
+            last_period = Some(period);
+            kept += 1;
+        }
+    }
+}
+
+fn period_of(utc_datetime: &DateTime<Utc>, reason: KeepReason) -> (i32, u32) {
+    match reason {
+        KeepReason::Last => (0, 0),
+        KeepReason::Daily => (utc_datetime.year(), utc_datetime.ordinal()),
+        KeepReason::Weekly => (utc_datetime.iso_week().year(), utc_datetime.iso_week().week()),
+        KeepReason::Monthly => (utc_datetime.year(), utc_datetime.month()),
+        KeepReason::Yearly => (utc_datetime.year(), 0)
+    }
+}
+
+
+// ----UNIT TESTS----
+
+#[cfg(test)]
+fn kept_names(plan: &RetentionPlan) -> Vec<(String, Vec<KeepReason>)> {
+    plan.keep.iter()
+        .map(|kept| (kept.path.to_str().unwrap().to_owned(), kept.reasons.clone()))
+        .collect()
+}
+
+#[test]
+fn retention_daily_and_monthly() {
+    let paths: Vec<PathBuf> = [
+        "db_2020-01-15T220000+0000.sql",
+        "db_2020-02-28T220000+0000.sql",
+        "db_2020-03-01T080000+0000.sql",
+        "db_2020-03-01T220000+0000.sql",
+        "db_2020-03-02T220000+0000.sql",
+        "notes.txt" ].iter().map(PathBuf::from).collect();
+
+    let policy = RetentionPolicy { keep_daily: 2, keep_monthly: 2, ..Default::default() };
+    let plan: RetentionPlan = apply_policy(&policy, &paths);
+
+    assert_eq!(kept_names(&plan), [
+        ("db_2020-02-28T220000+0000.sql".to_owned(), vec![KeepReason::Monthly]),
+        ("db_2020-03-01T220000+0000.sql".to_owned(), vec![KeepReason::Daily]),
+        ("db_2020-03-02T220000+0000.sql".to_owned(), vec![KeepReason::Daily, KeepReason::Monthly]) ]);
+    assert_eq!(plan.delete, [PathBuf::from("db_2020-01-15T220000+0000.sql"),
+                             PathBuf::from("db_2020-03-01T080000+0000.sql")]);
+}
+
+#[test]
+fn retention_groups_by_prefix() {
+    // Each prefix keeps its own newest file; and the periods use UTC, not the original offset.
+    let paths: Vec<PathBuf> = [
+        "db_2020-03-01T220000-0800.sql",
+        "db_2020-03-02T010000+0000.sql",
+        "web_2020-01-01T000000+0000.zip" ].iter().map(PathBuf::from).collect();
+
+    let policy = RetentionPolicy { keep_daily: 1, ..Default::default() };
+    let plan: RetentionPlan = apply_policy(&policy, &paths);
+
+    assert_eq!(kept_names(&plan), [
+        ("db_2020-03-01T220000-0800.sql".to_owned(), vec![KeepReason::Daily]),
+        ("web_2020-01-01T000000+0000.zip".to_owned(), vec![KeepReason::Daily]) ]);
+    assert_eq!(plan.delete, [PathBuf::from("db_2020-03-02T010000+0000.sql")]);
+}
+
+#[test]
+fn retention_with_sidecar_files() {
+    // A checksum beside the newest dump is its own group.  It must not take the place of the dump it describes.
+    let mut paths: Vec<PathBuf> = (1..=5).map(|day| PathBuf::from(format!("db_2020-03-0{}T120000+0000.sql", day))).collect();
+    paths.push(PathBuf::from("db_2020-03-05T120000+0000.sql.sha256"));
+
+    let policy = RetentionPolicy { keep_daily: 1, ..Default::default() };
+    let plan: RetentionPlan = apply_policy(&policy, &paths);
+    assert_eq!(kept_names(&plan), [
+        ("db_2020-03-05T120000+0000.sql".to_owned(), vec![KeepReason::Daily]),
+        ("db_2020-03-05T120000+0000.sql.sha256".to_owned(), vec![KeepReason::Daily]) ]);
+
+    let policy = RetentionPolicy { keep_last: 2, ..Default::default() };
+    let plan: RetentionPlan = apply_policy(&policy, &paths);
+    assert_eq!(kept_names(&plan), [
+        ("db_2020-03-04T120000+0000.sql".to_owned(), vec![KeepReason::Last]),
+        ("db_2020-03-05T120000+0000.sql".to_owned(), vec![KeepReason::Last]),
+        ("db_2020-03-05T120000+0000.sql.sha256".to_owned(), vec![KeepReason::Last]) ]);
+}
@@ -1,98 +1,644 @@
 // stamp.rs
 // This module is about stamping a String (or Path) with an ISO 8601 datetime.
 
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
-use super::*;
+use super::{find_stamp_bytes, parse_filename_bytes, parse_filename_parts, split_stamped_bytes, Error, StampedBytes};
+use super::osbytes;
+use super::dates::{fractional_digits, StampFormat};
+#[cfg(test)]
+use super::dates::datetime_to_iso_string;
 
 // 3rd Party
 use chrono::prelude::*;
+use chrono_tz::Tz;
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
 use serde_json::Result as ResultSerde;
 
 static EMPTY_STRING: &str = "";
+const PERIOD: &[u8] = b".";
 
 
-pub fn stamp_str(parm_file_name: &str, 
-	parm_date_time: &Option<DateTime<FixedOffset>>) -> String {
+// Which offset a stamp is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StampZone {
+	#[default]
+	Local,  // The local system's offset, at that instant.
+	Utc,
+	Fixed(FixedOffset),
+	Named(Tz)  // An IANA time zone, such as 'America/Los_Angeles'.  The offset depends on the instant (DST)
+}
+
+impl StampZone {
+	pub fn now(&self) -> DateTime<FixedOffset> {
+		// The current datetime, expressed in this zone.
+		self.convert(&Utc::now().into())
+	}
+
+	pub fn convert(&self, parm_date_time: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+		// Same instant, different offset.
+		match self {
+			StampZone::Local => parm_date_time.with_timezone(&Local).into(),
+			StampZone::Utc => parm_date_time.with_timezone(&Utc).into(),
+			StampZone::Fixed(offset) => parm_date_time.with_timezone(offset),
+			StampZone::Named(tz) => {
+				let zoned: DateTime<Tz> = parm_date_time.with_timezone(tz);
+				zoned.with_timezone(&zoned.offset().fix())
+			}
+		}
+	}
+}
 
-	// Either convert the argument, or get current UTC time.
-	let dt_string: String = datetime_to_iso_string(parm_date_time);
-	let filename: FileName = FileName::new(parm_file_name);
 
-	if filename.extension.is_none() {
-	return format!("{}_{}", filename.stem, dt_string);
+// Where the datetime goes, in a file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StampPlacement {
+	#[default]
+	AfterStem,  // stem_DATETIME.tar.gz
+	Front,  // DATETIME_stem.tar.gz  So that 'ls' sorts files chronologically.
+	End,  // stem.tar.gz_DATETIME
+	BeforeLastExtension  // stem.tar_DATETIME.gz
+}
+
+impl StampPlacement {
+	pub const ALL: [StampPlacement; 4] = [
+		StampPlacement::AfterStem, StampPlacement::Front, StampPlacement::End, StampPlacement::BeforeLastExtension
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			StampPlacement::AfterStem => "stem",
+			StampPlacement::Front => "front",
+			StampPlacement::End => "end",
+			StampPlacement::BeforeLastExtension => "last-ext"
+		}
 	}
 
-	format!("{}_{}.{}",
-		filename.stem,
-		dt_string,
-		filename.extension.unwrap_or(String::from(""))
-	)
+	pub fn from_name(name: &str) -> Option<StampPlacement> {
+		StampPlacement::ALL.iter().find(|placement| placement.name() == name).copied()
+	}
 }
 
-pub fn stamp_path(parm_path: &Path, 
-	parm_date_time: &Option<DateTime<FixedOffset>>) -> PathBuf {
 
-	let filename_as_str = parm_path.file_name().unwrap().to_str().unwrap();
+// What goes between the datetime and the rest of the file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StampSeparator {
+	#[default]
+	Underscore,  // stem_DATETIME
+	DoubleUnderscore,  // stem__DATETIME
+	Hyphen,  // stem-DATETIME
+	Period,  // stem.DATETIME
+	At  // stem@DATETIME
+}
+
+impl StampSeparator {
+	// Longest first, so that extraction strips '__' rather than only half of it.
+	pub const ALL: [StampSeparator; 5] = [
+		StampSeparator::DoubleUnderscore, StampSeparator::Underscore, StampSeparator::Hyphen,
+		StampSeparator::Period, StampSeparator::At
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			StampSeparator::Underscore => "_",
+			StampSeparator::DoubleUnderscore => "__",
+			StampSeparator::Hyphen => "-",
+			StampSeparator::Period => ".",
+			StampSeparator::At => "@"
+		}
+	}
 
-	// No parent in the Path.
-	if parm_path.parent().is_none() {
-		return PathBuf::from(stamp_str(filename_as_str, parm_date_time));
+	pub fn from_name(separator: &str) -> Option<StampSeparator> {
+		StampSeparator::ALL.iter().find(|value| value.as_str() == separator).copied()
 	}
+}
+
 
-	let parent_path = Path::new(parm_path.parent().unwrap());
-	parent_path.join(stamp_str(filename_as_str, parm_date_time))
+// Where the datetime of a stamp comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateTimeSource {
+	#[default]
+	Now,
+	Modified,  // mtime: the last change to the file's contents.
+	Changed,  // ctime: the last change to the file's metadata (such as a rename, or chmod).  Unix only.
+	Birth  // btime: when the file was created.  Only where the platform and filesystem record it.
 }
 
-// Private Functions
-fn datetime_to_iso_string(parm_date_time: &Option<DateTime<FixedOffset>>) -> String {
-	// Returns on owned String, that represents an ISO 8601 datetime value.
-	if parm_date_time.is_some() {
-		return parm_date_time.unwrap().to_iso_string();
+impl DateTimeSource {
+	pub const ALL: [DateTimeSource; 4] = [
+		DateTimeSource::Now, DateTimeSource::Modified, DateTimeSource::Changed, DateTimeSource::Birth
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			DateTimeSource::Now => "now",
+			DateTimeSource::Modified => "mtime",
+			DateTimeSource::Changed => "ctime",
+			DateTimeSource::Birth => "btime"
+		}
 	}
-	else {
-		return Utc::now().to_iso_string();
+
+	pub fn from_name(name: &str) -> Option<DateTimeSource> {
+		DateTimeSource::ALL.iter().find(|source| source.name() == name).copied()
+	}
+
+	pub fn datetime(&self, parm_path: &Path) -> Result<Option<DateTime<FixedOffset>>, Error> {
+		// Reads the datetime from a file's metadata, in local time.  'Now' is None; the current datetime, when stamping.
+		// The path may be the file being stamped, or another file entirely (like 'touch --reference').
+		match self {
+			DateTimeSource::Now => Ok(None),
+			DateTimeSource::Modified => modified_datetime(parm_path).map(Some),
+			DateTimeSource::Changed => changed_datetime(parm_path).map(Some),
+			DateTimeSource::Birth => {
+				let metadata: fs::Metadata = fs::metadata(parm_path).map_err(|error| Error::from_io(parm_path, error))?;
+				let created: DateTime<Local> = metadata.created().map_err(|error| Error::from_io(parm_path, error))?.into();
+				Ok(Some(created.into()))
+			}
+		}
 	}
 }
 
-trait Suffix8601Ext<'a> {
-    // Extension Trait! :)
-    fn to_iso_string(&self) -> String;
+
+// Options that control how a file name is stamped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StampOptions {
+	// When None, the current datetime is stamped in local time, and explicit datetimes keep their own offset.
+	// Otherwise, every datetime is converted to this zone before stamping.
+	pub zone: Option<StampZone>,
+	// When None, new stamps are Hybrid; and restamping keeps the profile (and precision) already in the name.
+	pub format: Option<StampFormat>,
+	pub zulu: bool,  // Write UTC datetimes with the 'Z' designator, instead of '+0000'
+	pub placement: StampPlacement,
+	pub separator: StampSeparator
 }
 
-impl<'a> Suffix8601Ext<'a> for DateTime<FixedOffset> {
-    fn to_iso_string(&self) -> String {
-        const ISO_FORMAT: &str = "%Y-%m-%dT%H%M%S%z";
-        self.format(ISO_FORMAT).to_string()  // DelayedFormat > String
-    }
+impl StampOptions {
+	pub fn resolve_datetime(&self, parm_date_time: &Option<DateTime<FixedOffset>>) -> DateTime<FixedOffset> {
+		// Either convert the argument, or get the current datetime.
+		// Resolve once, and pass the result to every call, so that a batch of files shares the same instant.
+		match (parm_date_time, self.zone) {
+			(Some(date_time), Some(zone)) => zone.convert(date_time),
+			(Some(date_time), None) => *date_time,
+			(None, zone) => zone.unwrap_or_default().now()
+		}
+	}
 }
 
-impl<'a> Suffix8601Ext<'a> for DateTime<Utc> {
-    fn to_iso_string(&self) -> String {
-        // to_rfc3339_opts() returns an owned String
-        self.to_rfc3339_opts(SecondsFormat::Secs, false).replace(':',"") 
-    }
+
+// What touch_from_stamp() did to 1 file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Touched {
+	#[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+	pub path: PathBuf,
+	pub stamp: DateTime<FixedOffset>,
+	pub changed: bool  // A time was different.  After a dry run, it still is.
+}
+
+
+pub fn stamp_str(parm_file_name: &str,
+	parm_date_time: &Option<DateTime<FixedOffset>>) -> Result<String, Error> {
+	stamp_str_with(parm_file_name, parm_date_time, &StampOptions::default())
+}
+
+pub fn stamp_str_with(parm_file_name: &str,
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<String, Error> {
+	osbytes::into_utf8(stamp_bytes_with(parm_file_name.as_bytes(), parm_date_time, options)?)
+}
+
+pub fn stamp_bytes_with(parm_file_name: &[u8],
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<Vec<u8>, Error> {
+	// Same as stamp_str_with(), for file names that are not necessarily UTF-8.
+	let dt_string: String = options.format.unwrap_or_default().format(&options.resolve_datetime(parm_date_time), options.zulu);
+	let dt: &[u8] = dt_string.as_bytes();
+	let (stem, extension) = parse_filename_bytes(parm_file_name)?;
+	let extension: &[u8] = extension.unwrap_or(b"");
+	let sep: &[u8] = options.separator.as_str().as_bytes();
+
+	// A period after a datetime at the front would be read back as the start of the extensions; so the name
+	// could never be extracted, or unstamped.
+	if options.placement == StampPlacement::Front && options.separator == StampSeparator::Period {
+		return Err(Error::InvalidFileName(osbytes::lossy(&[dt, sep, parm_file_name].concat())));
+	}
+
+	Ok(match (options.placement, extension) {
+		(StampPlacement::End, _) => [parm_file_name, sep, dt].concat(),
+		(StampPlacement::Front, b"") => [dt, sep, stem].concat(),
+		(StampPlacement::Front, _) => [dt, sep, stem, PERIOD, extension].concat(),
+		(_, b"") => [stem, sep, dt].concat(),
+		(StampPlacement::BeforeLastExtension, _) => match extension.iter().rposition(|byte| *byte == b'.') {
+			Some(index) => [stem, PERIOD, &extension[..index], sep, dt, PERIOD, &extension[index + 1..]].concat(),
+			None => [stem, sep, dt, PERIOD, extension].concat()
+		},
+		(StampPlacement::AfterStem, _) => [stem, sep, dt, PERIOD, extension].concat()
+	})
+}
+
+pub fn stamp_path(parm_path: &Path,
+	parm_date_time: &Option<DateTime<FixedOffset>>) -> Result<PathBuf, Error> {
+	stamp_path_with(parm_path, parm_date_time, &StampOptions::default())
+}
+
+pub fn stamp_path_with(parm_path: &Path,
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<PathBuf, Error> {
+	// Only the last component of the Path is stamped.  On Unix, it does not need to be UTF-8.
+	let file_name: &[u8] = osbytes::file_name_bytes(parm_path)?;
+	osbytes::with_file_name_bytes(parm_path, stamp_bytes_with(file_name, parm_date_time, options)?)
+}
+
+pub fn stamp_paths_with(parm_paths: &[PathBuf],
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Vec<Result<PathBuf, Error>> {
+	// Stamps a batch of Paths, in the same order.  Every one receives the same instant; even the current datetime.
+	let date_time: Option<DateTime<FixedOffset>> = Some(options.resolve_datetime(parm_date_time));
+	parm_paths.iter()
+		.map(|path| stamp_path_with(path, &date_time, options))
+		.collect()
+}
+
+pub fn stamp_path_from(parm_path: &Path,
+	source: DateTimeSource,
+	options: &StampOptions) -> Result<PathBuf, Error> {
+	// Same as stamp_path_with(), but the datetime comes from the file's own metadata.
+	stamp_path_with(parm_path, &source.datetime(parm_path)?, options)
+}
+
+pub fn restamp_str(parm_file_name: &str,
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<String, Error> {
+	// Replaces the datetime already in a file name, instead of adding a second one.
+	// The prefix, separator, and extensions are unchanged.
+	osbytes::into_utf8(restamp_bytes(parm_file_name.as_bytes(), parm_date_time, options)?)
+}
+
+pub fn restamp_bytes(parm_file_name: &[u8],
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<Vec<u8>, Error> {
+	// Same as restamp_str(), for file names that are not necessarily UTF-8.
+	let (start, end, format) = find_stamp_bytes(parm_file_name).ok_or_else(|| Error::NoStamp(osbytes::lossy(parm_file_name)))?;
+	Ok(replace_stamp(parm_file_name, start, end, format, &options.resolve_datetime(parm_date_time), options))
+}
+
+pub fn convert_stamp_str(parm_file_name: &str, options: &StampOptions) -> Result<String, Error> {
+	// Rewrites the datetime already in a file name, keeping the same instant.
+	// Use this to change its zone or format.  For example, to normalize every stamp to UTC.
+	osbytes::into_utf8(convert_stamp_bytes(parm_file_name.as_bytes(), options)?)
+}
+
+pub fn convert_stamp_bytes(parm_file_name: &[u8], options: &StampOptions) -> Result<Vec<u8>, Error> {
+	// Same as convert_stamp_str(), for file names that are not necessarily UTF-8.
+	let (start, end, format) = find_stamp_bytes(parm_file_name).ok_or_else(|| Error::NoStamp(osbytes::lossy(parm_file_name)))?;
+	let date_time: DateTime<FixedOffset> = std::str::from_utf8(&parm_file_name[start..end]).ok()
+		.and_then(|datetime_string| format.parse(datetime_string))
+		.ok_or_else(|| Error::InvalidDateTime(osbytes::lossy(&parm_file_name[start..end])))?;
+	Ok(replace_stamp(parm_file_name, start, end, format, &options.resolve_datetime(&Some(date_time)), options))
+}
+
+pub fn restamp_path(parm_path: &Path,
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions) -> Result<PathBuf, Error> {
+	let file_name: &[u8] = osbytes::file_name_bytes(parm_path)?;
+	osbytes::with_file_name_bytes(parm_path, restamp_bytes(file_name, parm_date_time, options)?)
+}
+
+pub fn convert_stamp_path(parm_path: &Path, options: &StampOptions) -> Result<PathBuf, Error> {
+	let file_name: &[u8] = osbytes::file_name_bytes(parm_path)?;
+	osbytes::with_file_name_bytes(parm_path, convert_stamp_bytes(file_name, options)?)
+}
+
+pub fn unstamp_str(parm_file_name: &str) -> Result<String, Error> {
+	// The inverse of stamp_str(): removes the datetime, and its separator, from a file name.
+	//   'report_2020-03-04T170054-0800.tar.gz'  --> 'report.tar.gz'
+	// A name that is only a datetime (and extensions) is an InvalidFileName; without it, nothing sensible is left.
+	osbytes::into_utf8(unstamp_bytes(parm_file_name.as_bytes())?)
+}
+
+pub fn unstamp_bytes(parm_file_name: &[u8]) -> Result<Vec<u8>, Error> {
+	// Same as unstamp_str(), for file names that are not necessarily UTF-8.
+	let stamped_name: StampedBytes = split_stamped_bytes(parm_file_name)?;
+	if stamped_name.prefix.is_empty() {
+		return Err(Error::InvalidFileName(osbytes::lossy(parm_file_name)));
+	}
+	match stamped_name.extension {
+		Some(extension) => Ok([&stamped_name.prefix, PERIOD, &extension].concat()),
+		None => Ok(stamped_name.prefix.into_owned())
+	}
+}
+
+pub fn unstamp_path(parm_path: &Path) -> Result<PathBuf, Error> {
+	// Same as unstamp_str(), but only the last component of the Path is changed.
+	let file_name: &[u8] = osbytes::file_name_bytes(parm_path)?;
+	osbytes::with_file_name_bytes(parm_path, unstamp_bytes(file_name)?)
+}
+
+pub fn rename_with_stamp(parm_path: &Path,
+	parm_date_time: &Option<DateTime<FixedOffset>>,
+	options: &StampOptions,
+	overwrite: bool) -> Result<PathBuf, Error> {
+	// Renames a file on disk, so that its name contains an ISO 8601 datetime.  Returns the new Path.
+	// Unless 'overwrite' is true, an existing file is never replaced.
+	let new_path: PathBuf = stamp_path_with(parm_path, parm_date_time, options)?;
+	rename_path(parm_path, &new_path, overwrite)?;
+	Ok(new_path)
+}
+
+pub fn rename_path(parm_path: &Path, new_path: &Path, overwrite: bool) -> Result<(), Error> {
+	// Renames a file on disk.  Unless 'overwrite' is true, an existing file is never replaced.
+	// Checking first, and then renaming, would let another process create 'new_path' in between.  So the file is
+	// hard linked to its new name (which fails if the name exists), and only then is the old name removed.
+	// A file renamed to its own name (such as a UTC stamp converted to UTC) is left alone.
+	if parm_path == new_path {
+		return check_rename(parm_path, new_path, overwrite);
+	}
+	if overwrite {
+		return fs::rename(parm_path, new_path).map_err(|error| Error::from_io(parm_path, error));
+	}
+	match fs::hard_link(parm_path, new_path) {
+		Ok(()) => fs::remove_file(parm_path).map_err(|error| Error::from_io(parm_path, error)),
+		Err(error) if error.kind() == io::ErrorKind::AlreadyExists => Err(Error::AlreadyExists(new_path.to_path_buf())),
+		Err(error) if error.kind() == io::ErrorKind::NotFound => Err(Error::from_io(parm_path, error)),
+		Err(_) => {
+			// Directories, and some filesystems (FAT, many network shares), cannot be hard linked.
+			check_rename(parm_path, new_path, overwrite)?;
+			fs::rename(parm_path, new_path).map_err(|error| Error::from_io(parm_path, error))
+		}
+	}
+}
+
+pub fn check_rename(parm_path: &Path, new_path: &Path, overwrite: bool) -> Result<(), Error> {
+	// The checks rename_path() makes, without renaming anything.  For dry runs.
+	// symlink_metadata() also detects dangling symbolic links, which exists() would not.
+	fs::symlink_metadata(parm_path).map_err(|error| Error::from_io(parm_path, error))?;
+	if !overwrite && parm_path != new_path && fs::symlink_metadata(new_path).is_ok() {
+		return Err(Error::AlreadyExists(new_path.to_path_buf()));
+	}
+	Ok(())
+}
+
+pub fn modified_datetime(parm_path: &Path) -> Result<DateTime<FixedOffset>, Error> {
+	// Returns the last modification time of a file, from the filesystem metadata.  In local time.
+	let modified: DateTime<Local> = fs::metadata(parm_path)
+		.and_then(|metadata| metadata.modified())
+		.map_err(|error| Error::from_io(parm_path, error))?
+		.into();
+	Ok(modified.into())
+}
+
+pub fn stamped_datetime(parm_path: &Path) -> Result<DateTime<FixedOffset>, Error> {
+	// Returns the datetime in the last component of a Path.  On Unix, it does not need to be UTF-8.
+	let file_name: &[u8] = osbytes::file_name_bytes(parm_path)?;
+	Ok(split_stamped_bytes(file_name)?.datetime)
+}
+
+pub fn touch_from_stamp(parm_path: &Path, set_atime: bool, dry_run: bool) -> Result<Touched, Error> {
+	// Sets a file's modification time (and optionally its access time) to the datetime in its file name.
+	// 'changed' is true if a time was different.  With 'dry_run', nothing is changed; but the result is the same.
+	let date_time: DateTime<FixedOffset> = stamped_datetime(parm_path)?;
+	let stamp_time = FileTime::from_unix_time(date_time.timestamp(), date_time.timestamp_subsec_nanos());
+
+	let metadata: fs::Metadata = fs::metadata(parm_path).map_err(|error| Error::from_io(parm_path, error))?;
+	let is_different: bool = FileTime::from_last_modification_time(&metadata) != stamp_time
+		|| (set_atime && FileTime::from_last_access_time(&metadata) != stamp_time);
+	let touched = Touched { path: parm_path.to_path_buf(), stamp: date_time, changed: is_different };
+	if !is_different || dry_run {
+		return Ok(touched);
+	}
+
+	let result = if set_atime {
+		filetime::set_file_times(parm_path, stamp_time, stamp_time)
+	}
+	else {
+		filetime::set_file_mtime(parm_path, stamp_time)
+	};
+	result.map_err(|error| Error::from_io(parm_path, error))?;
+	Ok(touched)
+}
+
+// ---- PRIVATE FUNCTIONS -----
+
+#[cfg(unix)]
+fn changed_datetime(parm_path: &Path) -> Result<DateTime<FixedOffset>, Error> {
+	// The status-change time.  Standard Rust has no portable accessor for it, so it comes from the Unix stat.
+	use std::os::unix::fs::MetadataExt;
+	let metadata: fs::Metadata = fs::metadata(parm_path).map_err(|error| Error::from_io(parm_path, error))?;
+	let changed: DateTime<Utc> = DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32)
+		.ok_or_else(|| Error::InvalidDateTime(metadata.ctime().to_string()))?;
+	Ok(changed.with_timezone(&Local).into())
 }
 
+#[cfg(not(unix))]
+fn changed_datetime(parm_path: &Path) -> Result<DateTime<FixedOffset>, Error> {
+	let error = std::io::Error::new(std::io::ErrorKind::Unsupported, "the status-change time (ctime) only exists on Unix");
+	Err(Error::from_io(parm_path, error))
+}
+
+fn replace_stamp(parm_file_name: &[u8], start: usize, end: usize, found_format: StampFormat,
+	date_time: &DateTime<FixedOffset>, options: &StampOptions) -> Vec<u8> {
+	// Writes the datetime over bytes 'start..end'.  Unless a format was chosen, the one found there is kept.
+	let dt_string: String = match options.format {
+		Some(format) => format.format(date_time, options.zulu),
+		None => {
+			let found: &str = std::str::from_utf8(&parm_file_name[start..end]).unwrap_or("");
+			let zulu: bool = options.zulu || found.ends_with('Z');
+			found_format.format_with_fraction(date_time, zulu, fractional_digits(found))
+		}
+	};
+	[&parm_file_name[..start], dt_string.as_bytes(), &parm_file_name[end..]].concat()
+}
 
 // ----UNIT TESTS----
 
 #[test]
 fn test_get_datetime_string() {
-    let dt_naive: NaiveDateTime = NaiveDate::from_ymd(1955, 11, 5).and_hms(9, 10, 11);
+    let dt_naive: NaiveDateTime = NaiveDate::from_ymd_opt(1955, 11, 5).unwrap().and_hms_opt(9, 10, 11).unwrap();
 
-    let utc_datetime: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
-    let no_offset = FixedOffset::west(0);
+    let utc_datetime: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
+    let no_offset = FixedOffset::west_opt(0).unwrap();
     let utc_minus_0 = utc_datetime.with_timezone(&no_offset);
     assert_eq!( datetime_to_iso_string(&Some(utc_minus_0)), "1955-11-05T091011+0000");
 
-    let offset = FixedOffset::west(8 * 3600);
+    let offset = FixedOffset::west_opt(8 * 3600).unwrap();
     let utc_minus_8 = utc_datetime.with_timezone(&offset);
     assert_eq!( datetime_to_iso_string(&Some(utc_minus_8)), "1955-11-05T011011-0800");
 }
 
 
-use serde::Serialize;
+#[test]
+fn stamp_with_zone() {
+    let some_datetime = crate::datetime_from_string("2020-03-04T170054-0800").ok();
+
+    // Without a zone, an explicit datetime keeps its own offset.
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &StampOptions::default()).unwrap(),
+               "db_2020-03-04T170054-0800.sql");
+
+    let utc = StampOptions { zone: Some(StampZone::Utc), ..Default::default() };
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &utc).unwrap(), "db_2020-03-05T010054+0000.sql");
+
+    let india = StampOptions { zone: Some(StampZone::Fixed(FixedOffset::east_opt(5 * 3600 + 1800).unwrap())), ..Default::default() };
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &india).unwrap(), "db_2020-03-05T063054+0530.sql");
+
+    let zulu = StampOptions { zone: Some(StampZone::Utc), format: Some(StampFormat::Basic), zulu: true, ..Default::default() };
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &zulu).unwrap(), "db_20200305T010054Z.sql");
+
+    // Named zones choose the offset that applies at that instant.
+    let berlin = StampOptions { zone: Some(StampZone::Named(chrono_tz::Europe::Berlin)), ..Default::default() };
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &berlin).unwrap(), "db_2020-03-05T020054+0100.sql");
+    let summer_datetime = crate::datetime_from_string("2020-07-04T170054-0700").ok();
+    assert_eq!(stamp_str_with("db.sql", &summer_datetime, &berlin).unwrap(), "db_2020-07-05T020054+0200.sql");
+
+    // The current datetime is stamped with the local system's offset.
+    assert_eq!(StampZone::default().now().offset(), Local::now().offset());
+}
+
+
+#[test]
+fn stamp_batch_shares_instant() {
+    // Even with millisecond precision, every file in the batch receives an identical stamp.
+    let paths: Vec<PathBuf> = (0..500).map(|index| PathBuf::from(format!("/backups/part{}.tar.gz", index))).collect();
+    let options = StampOptions { format: Some(StampFormat::Milliseconds), ..Default::default() };
+    let stamped: Vec<PathBuf> = stamp_paths_with(&paths, &None, &options).into_iter().map(Result::unwrap).collect();
+
+    let datetime_strings: Vec<String> = stamped.iter()
+        .map(|path| crate::split_stamped_name(path.file_name().unwrap().to_str().unwrap()).unwrap().datetime_string.to_owned())
+        .collect();
+    assert!(datetime_strings.iter().all(|each| *each == datetime_strings[0]));
+    assert_eq!(stamped[7], PathBuf::from(format!("/backups/part7_{}.tar.gz", datetime_strings[0])));
+}
+
+
+#[test]
+fn datetime_sources() {
+    // The stamp comes from the file's own metadata; or, with 'Now', the current datetime.
+    let temporary = tempfile::tempdir().unwrap();
+    let path: PathBuf = temporary.path().join("source.sql");
+    fs::write(&path, "").unwrap();
+    let modified: DateTime<FixedOffset> = fs::metadata(&path).unwrap().modified().map(DateTime::<Local>::from).unwrap().into();
+
+    assert_eq!(DateTimeSource::Now.datetime(&path).unwrap(), None);
+    assert_eq!(DateTimeSource::Modified.datetime(&path).unwrap(), Some(modified));
+    #[cfg(unix)]
+    assert!(DateTimeSource::Changed.datetime(&path).unwrap().is_some());
+
+    let options = StampOptions { format: Some(StampFormat::Microseconds), ..Default::default() };
+    let stamped: PathBuf = stamp_path_from(&path, DateTimeSource::Modified, &options).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(stamped, stamp_path_with(&path, &Some(modified), &options).unwrap());
+
+    assert!(matches!(DateTimeSource::Modified.datetime(&path), Err(Error::NotFound(_))));
+    assert_eq!(DateTimeSource::from_name("btime"), Some(DateTimeSource::Birth));
+}
+
+
+#[test]
+fn stamp_with_placement() {
+    let some_datetime = crate::datetime_from_string("2020-03-04T170054-0800").ok();
+    let expected = [ (StampPlacement::AfterStem, "db_2020-03-04T170054-0800.tar.gz", "db_2020-03-04T170054-0800"),
+                     (StampPlacement::Front, "2020-03-04T170054-0800_db.tar.gz", "2020-03-04T170054-0800_db"),
+                     (StampPlacement::End, "db.tar.gz_2020-03-04T170054-0800", "db_2020-03-04T170054-0800"),
+                     (StampPlacement::BeforeLastExtension, "db.tar_2020-03-04T170054-0800.gz", "db_2020-03-04T170054-0800") ];
+
+    for (placement, with_extensions, without_extensions) in expected.iter() {
+        let options = StampOptions { placement: *placement, ..Default::default() };
+        assert_eq!(stamp_str_with("db.tar.gz", &some_datetime, &options).unwrap(), *with_extensions);
+        assert_eq!(stamp_str_with("db", &some_datetime, &options).unwrap(), *without_extensions);
+
+        // The extractor must recognize every placement.
+        let stamped_name = crate::split_stamped_name(with_extensions).unwrap();
+        assert_eq!(stamped_name.prefix, "db");
+        assert_eq!(stamped_name.extension.as_deref(), Some("tar.gz"));
+    }
+}
+
+
+#[test]
+fn stamp_with_separator() {
+    let some_datetime = crate::datetime_from_string("2020-03-04T170054-0800").ok();
+    for separator in StampSeparator::ALL.iter() {
+        let options = StampOptions { separator: *separator, ..Default::default() };
+        let stamped: String = stamp_str_with("my-db.tar.gz", &some_datetime, &options).unwrap();
+        assert_eq!(stamped, format!("my-db{}2020-03-04T170054-0800.tar.gz", separator.as_str()));
+
+        // Extraction strips the separator, whichever it is.
+        let stamped_name = crate::split_stamped_name(&stamped).unwrap();
+        assert_eq!(stamped_name.prefix, "my-db");
+        assert_eq!(stamped_name.extension.as_deref(), Some("tar.gz"));
+    }
+
+    let front = StampOptions { separator: StampSeparator::At, placement: StampPlacement::Front, ..Default::default() };
+    assert_eq!(stamp_str_with("db.sql", &some_datetime, &front).unwrap(), "2020-03-04T170054-0800@db.sql");
+    assert_eq!(crate::split_stamped_name("2020-03-04T170054-0800@db.sql").unwrap().prefix, "db");
+
+    // A period cannot follow a datetime at the front; it would begin the extensions.
+    let front = StampOptions { separator: StampSeparator::Period, placement: StampPlacement::Front, ..Default::default() };
+    assert!(matches!(stamp_str_with("x.tar.gz", &some_datetime, &front), Err(Error::InvalidFileName(_))));
+}
+
+
+#[test]
+fn restamp_and_convert() {
+    let some_datetime = crate::datetime_from_string("2021-01-02T030405+0000").ok();
+    let options = StampOptions::default();
+    assert_eq!(restamp_str("db_2020-03-04T170054-0800.tar.gz", &some_datetime, &options).unwrap(),
+               "db_2021-01-02T030405+0000.tar.gz");
+    assert_eq!(restamp_str("2020-03-04T170054-0800@db_full.sql", &some_datetime, &options).unwrap(),
+               "2021-01-02T030405+0000@db_full.sql");
+    assert!(matches!(restamp_str("db.tar.gz", &some_datetime, &options), Err(Error::NoStamp(_))));
+
+    // The same instant, in UTC.
+    let utc = StampOptions { zone: Some(StampZone::Utc), ..Default::default() };
+    assert_eq!(convert_stamp_str("db_2020-03-04T170054-0800.tar.gz", &utc).unwrap(),
+               "db_2020-03-05T010054+0000.tar.gz");
+    assert_eq!(convert_stamp_path(Path::new("/backups/db.tar_2020-03-04T170054-0800.gz"), &utc).unwrap(),
+               PathBuf::from("/backups/db.tar_2020-03-05T010054+0000.gz"));
+
+    // Without a chosen format, the profile, precision, and 'Z' already in the name are kept.
+    assert_eq!(convert_stamp_str("db_20200304T170054-0800.sql", &utc).unwrap(), "db_20200305T010054+0000.sql");
+    assert_eq!(convert_stamp_str("db_2020-03-04.sql", &utc).unwrap(), "db_2020-03-04.sql");
+    assert_eq!(convert_stamp_str("db_2020-03-04T170054.250-0800.sql", &utc).unwrap(), "db_2020-03-05T010054.250+0000.sql");
+    assert_eq!(convert_stamp_str("db_2024-01-02T030405.1Z.sql", &options).unwrap(), "db_2024-01-02T030405.1Z.sql");
+    assert_eq!(restamp_str("db_2020-03-04T1700-0800.sql", &some_datetime, &options).unwrap(), "db_2021-01-02T0304+0000.sql");
+
+    let basic = StampOptions { format: Some(StampFormat::Basic), ..utc };
+    assert_eq!(convert_stamp_str("db_2020-03-04T170054.250-0800.sql", &basic).unwrap(), "db_20200305T010054+0000.sql");
+}
+
+
+#[test]
+fn unstamp() {
+    assert_eq!(unstamp_str("report_2020-03-04T170054-0800.tar.gz").unwrap(), "report.tar.gz");
+    assert_eq!(unstamp_str("2020-03-04T170054-0800@report.sql").unwrap(), "report.sql");
+    assert_eq!(unstamp_str("report.tar_2020-03-04T170054-0800.gz").unwrap(), "report.tar.gz");
+    assert_eq!(unstamp_str("report-2020-03-04T170054-0800").unwrap(), "report");
+    assert_eq!(unstamp_path(Path::new("/backups/db_2020-03-04T170054-0800.sql")).unwrap(),
+               PathBuf::from("/backups/db.sql"));
+
+    assert!(matches!(unstamp_str("report.tar.gz"), Err(Error::NoStamp(_))));
+    assert!(matches!(unstamp_str("2020-03-04T170054-0800.tar.gz"), Err(Error::InvalidFileName(_))));
+
+    // Stamping, then unstamping, gives back the original name.  For every placement and separator that is allowed.
+    let some_datetime = crate::datetime_from_string("2020-03-04T170054-0800").ok();
+    for placement in StampPlacement::ALL.iter() {
+        for separator in StampSeparator::ALL.iter() {
+            let options = StampOptions { placement: *placement, separator: *separator, ..Default::default() };
+            for file_name in ["report.tar.gz", "report"] {
+                let stamped = stamp_str_with(file_name, &some_datetime, &options);
+                if *placement == StampPlacement::Front && *separator == StampSeparator::Period {
+                    assert!(matches!(stamped, Err(Error::InvalidFileName(_))));
+                    continue;
+                }
+                let stamped: String = stamped.unwrap();
+                assert_eq!(unstamp_str(&stamped).unwrap(), file_name, "{:?} {:?}", placement, separator);
+                assert_eq!(crate::split_stamped_name(&stamped).unwrap().prefix, "report", "{}", stamped);
+            }
+        }
+    }
+}
+
 
 // New Struct
 #[derive(Serialize)]
@@ -104,28 +650,25 @@ pub struct FileName<'a> {
 
 #[allow(dead_code)]
 impl<'a> FileName<'a> {
-    pub fn new(some_string: &str) -> FileName {
-        let filename_parts: (&str, Option<&str>) = parse_filename_parts(some_string);
+    pub fn new(some_string: &str) -> Result<FileName<'_>, Error> {
+        let filename_parts: (&str, Option<&str>) = parse_filename_parts(some_string)?;
 
-        let mut ext: Option<String> = None;
-        if filename_parts.1.is_some() {
-            ext = Some(filename_parts.1.unwrap().to_owned());
-        }
+        let ext: Option<String> = filename_parts.1.map(|value| value.to_owned());
 
         let filename_new = FileName {
             stem: filename_parts.0.to_owned(),
             extension: ext,
             directory: Path::new(some_string).parent(),
         };
-        filename_new
+        Ok(filename_new)
     }
 
-    fn print_as_json(&self) -> () {
+    fn print_as_json(&self) {
         // Serialize it to a JSON string.
         let j: ResultSerde<String> = serde_json::to_string(&self);
 
-        if j.is_ok() {
-            println!("{}", j.unwrap());
+        if let Ok(value) = j {
+            println!("{}", value);
         }
     }
 
@@ -144,6 +687,6 @@ impl<'a> FileName<'a> {
 
 #[test]
 fn filename_to_string() {
-    let some_file_name: FileName = FileName::new("some_file_name.tar.gz");
+    let some_file_name: FileName = FileName::new("some_file_name.tar.gz").unwrap();
     assert_eq!("some_file_name.tar.gz", some_file_name.to_owned_str());
 }
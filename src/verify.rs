@@ -0,0 +1,109 @@
+// verify.rs
+// This module is about comparing the datetime in a file's name with its modification time.
+//
+// A backup that was re-copied (or mislabelled) has a modification time far from its stamp.  The difference is the
+// 'delta': positive when the file was modified after the datetime in its name.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// 3rd Party
+use chrono::prelude::*;
+use chrono::Duration;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::stamp::{modified_datetime, stamped_datetime};
+use super::Error;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Ok,  // Within the tolerance.
+    Drift  // The modification time differs from the stamp by more than the tolerance.
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name: &str = match self {
+            Verdict::Ok => "ok",
+            Verdict::Drift => "drift"
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Verification {
+    #[serde(serialize_with = "crate::osbytes::serialize_lossy")]
+    pub path: PathBuf,
+    pub stamp: DateTime<FixedOffset>,
+    pub mtime: DateTime<FixedOffset>,  // In local time.
+    pub delta_seconds: f64,  // mtime - stamp
+    pub verdict: Verdict
+}
+
+impl Verification {
+    pub fn delta(&self) -> Duration {
+        self.mtime.signed_duration_since(self.stamp)
+    }
+
+    pub fn delta_string(&self) -> String {
+        // The delta for humans, such as '+2h03m05s' or '-0.250s'.
+        let delta: Duration = self.delta();
+        let sign: &str = if delta < Duration::zero() { "-" } else { "+" };
+        let delta: Duration = if delta < Duration::zero() { -delta } else { delta };
+        let seconds: i64 = delta.num_seconds();
+        if seconds < 60 {
+            return format!("{}{}.{:03}s", sign, seconds, delta.num_milliseconds() % 1000);
+        }
+        match (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60) {
+            (0, 0, minutes, seconds) => format!("{}{}m{:02}s", sign, minutes, seconds),
+            (0, hours, minutes, seconds) => format!("{}{}h{:02}m{:02}s", sign, hours, minutes, seconds),
+            (days, hours, minutes, _) => format!("{}{}d{:02}h{:02}m", sign, days, hours, minutes)
+        }
+    }
+}
+
+
+pub fn verify_path(path: &Path, tolerance: Duration) -> Result<Verification, Error> {
+    // Compares the datetime in a file's name with its modification time.
+    let stamp: DateTime<FixedOffset> = stamped_datetime(path)?;
+    let mtime: DateTime<FixedOffset> = modified_datetime(path)?;
+    let delta: Duration = mtime.signed_duration_since(stamp);
+    let delta_seconds: f64 = match delta.num_nanoseconds() {
+        Some(nanoseconds) => nanoseconds as f64 / 1e9,
+        None => delta.num_seconds() as f64  // More than 292 years.
+    };
+    let verdict: Verdict = if delta > tolerance || delta < -tolerance { Verdict::Drift } else { Verdict::Ok };
+    Ok(Verification { path: path.to_path_buf(), stamp, mtime, delta_seconds, verdict })
+}
+
+
+pub fn verify_paths(paths: &[PathBuf], tolerance: Duration) -> Vec<Result<Verification, Error>> {
+    // Same as verify_path(), for many files, in the same order.  The files are read in parallel.
+    paths.par_iter()
+        .map(|path| verify_path(path, tolerance))
+        .collect()
+}
+
+
+// ----UNIT TESTS----
+
+#[test]
+fn delta_strings() {
+    let stamp: DateTime<FixedOffset> = crate::datetime_from_string("2020-03-04T170054-0800").unwrap();
+    let with_delta = |delta: Duration| Verification {
+        path: PathBuf::from("db_2020-03-04T170054-0800.sql"),
+        stamp,
+        mtime: stamp + delta,
+        delta_seconds: 0.0,
+        verdict: Verdict::Ok
+    };
+    assert_eq!(with_delta(Duration::milliseconds(-250)).delta_string(), "-0.250s");
+    assert_eq!(with_delta(Duration::seconds(59)).delta_string(), "+59.000s");
+    assert_eq!(with_delta(Duration::seconds(125)).delta_string(), "+2m05s");
+    assert_eq!(with_delta(Duration::seconds(7385)).delta_string(), "+2h03m05s");
+    assert_eq!(with_delta(-Duration::days(3) - Duration::minutes(7)).delta_string(), "-3d00h07m");
+}
@@ -0,0 +1,160 @@
+// walk.rs
+// This module is about finding files beneath a directory: how deep to look, which names to include or exclude,
+// and whether to follow symbolic links.
+//
+// Large trees (millions of files) are walked in parallel, with Rayon.  Output is always ordered by path.
+//
+// A directory tree is rarely perfect.  So an entry that cannot be read (or a symbolic link loop) becomes a
+// warning, and the walk carries on.  Only the starting directory itself must be readable.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 3rd Party
+use glob::{MatchOptions, Pattern};
+use rayon::prelude::*;
+
+use super::Error;
+
+
+// Patterns match the file name.  Unless they contain a '/'; then they match the path below the starting directory.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false
+};
+
+
+#[derive(Clone, Debug, Default)]
+pub struct WalkOptions {
+    pub max_depth: Option<usize>,  // 1 is the directory's own files; so is 0.  None has no limit.
+    pub include: Vec<Pattern>,  // When not empty, files must match at least 1 of these.
+    pub exclude: Vec<Pattern>,  // Files and directories matching any of these are skipped.
+    pub follow_symlinks: bool  // Descend into symbolic links to directories.
+}
+
+impl WalkOptions {
+    pub fn single_level() -> WalkOptions {
+        // Only the directory's own files, like 'ls'.
+        WalkOptions { max_depth: Some(1), ..Default::default() }
+    }
+}
+
+
+#[derive(Debug, Default)]
+pub struct Walk {
+    pub files: Vec<PathBuf>,  // Ordered by path.
+    pub warnings: Vec<Error>  // Entries that were skipped, because they could not be read.
+}
+
+
+pub fn walk_directory(directory: &Path, options: &WalkOptions) -> Result<Walk, Error> {
+    // Returns the paths of every matching file beneath a directory.
+    // Subdirectories are walked in parallel, on Rayon's current thread pool.  To limit the threads, call this
+    // inside ThreadPool::install().  The result is the same, whatever the number of threads.
+    let entries: fs::ReadDir = fs::read_dir(directory).map_err(|error| Error::from_io(directory, error))?;
+
+    // Canonical paths of the directories being walked; a symbolic link back to one of them is a loop.
+    let mut ancestors: Vec<PathBuf> = Vec::new();
+    if options.follow_symlinks {
+        ancestors.push(fs::canonicalize(directory).map_err(|error| Error::from_io(directory, error))?);
+    }
+
+    let mut walk: Walk = walk_entries(directory, directory, entries, 1, options, &ancestors);
+    walk.files.par_sort_unstable();
+    Ok(walk)
+}
+
+
+// ---- PRIVATE FUNCTIONS -----
+
+fn walk_entries(root: &Path, directory: &Path, entries: fs::ReadDir, depth: usize, options: &WalkOptions,
+                ancestors: &[PathBuf]) -> Walk {
+    // Visits the entries of 1 directory, and then its subdirectories in parallel.  'depth' is the depth of these entries.
+    // Nothing is shared between threads; each subdirectory returns its own Walk, and they are joined in path order.
+    let mut walk = Walk::default();
+    let mut sorted_entries: Vec<fs::DirEntry> = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => sorted_entries.push(entry),
+            Err(error) => walk.warnings.push(Error::from_io(directory, error))
+        }
+    }
+    sorted_entries.sort_by_key(fs::DirEntry::path);  // read_dir() makes no promises about ordering.
+
+    let mut subdirectories: Vec<PathBuf> = Vec::new();
+    for entry in sorted_entries {
+        let path: PathBuf = entry.path();
+        if is_match(root, &path, &options.exclude) {
+            continue;
+        }
+
+        // DirEntry::file_type() does not follow symbolic links.  But fs::metadata() does.
+        let is_symlink: bool = entry.file_type().map(|file_type| file_type.is_symlink()).unwrap_or(false);
+        let metadata: fs::Metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) if is_symlink => continue,  // A dangling symbolic link is not a file.
+            Err(error) => {
+                walk.warnings.push(Error::from_io(&path, error));
+                continue;
+            }
+        };
+
+        if metadata.is_file() {
+            if options.include.is_empty() || is_match(root, &path, &options.include) {
+                walk.files.push(path);
+            }
+        }
+        else if metadata.is_dir() && !matches!(options.max_depth, Some(max_depth) if depth >= max_depth) {
+            if is_symlink && !options.follow_symlinks {
+                continue;
+            }
+            subdirectories.push(path);
+        }
+    }
+
+    let visited: Vec<Walk> = subdirectories.par_iter()
+        .map(|subdirectory| walk_subdirectory(root, subdirectory, depth + 1, options, ancestors))
+        .collect();
+    for each in visited {
+        walk.files.extend(each.files);
+        walk.warnings.extend(each.warnings);
+    }
+    walk
+}
+
+fn walk_subdirectory(root: &Path, directory: &Path, depth: usize, options: &WalkOptions, ancestors: &[PathBuf]) -> Walk {
+    // Descends into a subdirectory; unless it cannot be read, or would start a loop.
+    let warning = |error: Error| Walk { files: Vec::new(), warnings: vec![error] };
+
+    let mut ancestors: Vec<PathBuf> = ancestors.to_vec();
+    if options.follow_symlinks {
+        let canonical: PathBuf = match fs::canonicalize(directory) {
+            Ok(canonical) => canonical,
+            Err(error) => return warning(Error::from_io(directory, error))
+        };
+        if ancestors.contains(&canonical) {
+            return warning(Error::SymlinkLoop(directory.to_path_buf()));
+        }
+        ancestors.push(canonical);
+    }
+
+    match fs::read_dir(directory) {
+        Ok(entries) => walk_entries(root, directory, entries, depth, options, &ancestors),
+        Err(error) => warning(Error::from_io(directory, error))
+    }
+}
+
+fn is_match(root: &Path, path: &Path, patterns: &[Pattern]) -> bool {
+    // True when any pattern matches the path's file name; or for patterns with a '/', its path below 'root'.
+    let relative: &Path = path.strip_prefix(root).unwrap_or(path);
+    let file_name: &Path = path.file_name().map(Path::new).unwrap_or(relative);
+    patterns.iter().any(|pattern| {
+        if pattern.as_str().contains('/') {
+            pattern.matches_path_with(relative, MATCH_OPTIONS)
+        }
+        else {
+            pattern.matches_path_with(file_name, MATCH_OPTIONS)
+        }
+    })
+}
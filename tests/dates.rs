@@ -8,32 +8,32 @@ use chrono::prelude::*;
 
 #[test]
 fn test_utc_naive() {
-    let dt_naive: NaiveDateTime = NaiveDate::from_ymd(1955, 11, 5).and_hms(20, 10, 5);
-    let utc_from_naive: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
+    let dt_naive: NaiveDateTime = NaiveDate::from_ymd_opt(1955, 11, 5).unwrap().and_hms_opt(20, 10, 5).unwrap();
+    let utc_from_naive: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
     assert_eq!(utc_from_naive.to_string(), "1955-11-05 20:10:05 UTC");
 }
 
 #[test]
 fn test_utc_nooffset() {
-    let dt_naive: NaiveDateTime = NaiveDate::from_ymd(1955, 11, 5).and_hms(20, 10, 5);
-    let utc_from_naive: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
-    let no_offset = FixedOffset::east(0);
+    let dt_naive: NaiveDateTime = NaiveDate::from_ymd_opt(1955, 11, 5).unwrap().and_hms_opt(20, 10, 5).unwrap();
+    let utc_from_naive: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
+    let no_offset = FixedOffset::east_opt(0).unwrap();
     let fixed_from_utc = utc_from_naive.with_timezone(&no_offset);
     assert_eq!(fixed_from_utc.to_string(), "1955-11-05 20:10:05 +00:00");
 }
 
 #[test]
 fn test_utc_offset_west8() {
-    let dt_naive: NaiveDateTime = NaiveDate::from_ymd(1955, 11, 5).and_hms(20, 10, 5);
-    let utc_from_naive: DateTime<Utc> = DateTime::<Utc>::from_utc(dt_naive, Utc);
-    let offset = FixedOffset::west(8 * 3600);
+    let dt_naive: NaiveDateTime = NaiveDate::from_ymd_opt(1955, 11, 5).unwrap().and_hms_opt(20, 10, 5).unwrap();
+    let utc_from_naive: DateTime<Utc> = Utc.from_utc_datetime(&dt_naive);
+    let offset = FixedOffset::west_opt(8 * 3600).unwrap();
     let fixed_from_utc = utc_from_naive.with_timezone(&offset);
     assert_eq!(fixed_from_utc.to_string(), "1955-11-05 12:10:05 -08:00");
 }
 
 #[test]
 fn test_zero_offsets() {
-    let east_zero = FixedOffset::east(0);
-    let west_zero = FixedOffset::west(0);
+    let east_zero = FixedOffset::east_opt(0).unwrap();
+    let west_zero = FixedOffset::west_opt(0).unwrap();
     assert_eq!(east_zero, west_zero);
 }
@@ -1,12 +1,15 @@
 // integration.rs
 
 // Standard
+use std::fs;
 use std::path::{Path};
 
 // Third Party
 use chrono::prelude::*;
 
-use filedate;
+use filedate::Error;
+use filedate::metadata::{DatedPathBuf, FileNameMeta};
+
 
 // https://doc.rust-lang.org/book/ch11-01-writing-tests.html
 // You can enable stdout with command syntax 'cargo test -- --nocapture'
@@ -24,7 +27,7 @@ fn test_std_path_library() {
 #[test]
 fn suffix_test() {
     const DATETIME_STRING: &str = "1955-11-05T011011-0800";
-    let some_datetime: Option<DateTime<FixedOffset>> = filedate::datetime_from_string(DATETIME_STRING);
+    let some_datetime: Option<DateTime<FixedOffset>> = filedate::datetime_from_string(DATETIME_STRING).ok();
     let filenames = ["some_file_name",
                         ".some_file_name",
                         "some_file_name.gz",
@@ -39,6 +42,365 @@ fn suffix_test() {
                         "some_file_name_1955-11-05T011011-0800.tar.gz"];
                     
     for (i, x) in filenames.iter().enumerate() {
-        assert_eq!( expected[i], filedate::stamp::stamp_str(&x, &some_datetime));
+        assert_eq!( expected[i], filedate::stamp::stamp_str(x, &some_datetime).unwrap());
+    }
+}
+
+#[test]
+fn metadata_from_directory() {
+    // Only stamped files become metadata objects.  Everything else in the directory is skipped.
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    for name in ["db_2020-03-05T170054-0800.tar.gz", "db_2020-03-04T170054-0800.tar.gz",
+                 "db_2020-03-06T170054-0800.tar.gz", "web_2020-03-01T090000+0000.zip", "notes.txt"] {
+        fs::write(directory.join(name), "").unwrap();
+    }
+    let result = filedate::build_metadata_from_path(directory, true);
+
+    let metadata: Vec<FileNameMeta> = serde_json::from_str(&result.unwrap()).unwrap();
+    assert_eq!(metadata.len(), 4);
+    assert_eq!(metadata[0].full_name, "db_2020-03-04T170054-0800.tar.gz");
+    assert_eq!(metadata[0].prefix, "db");
+    assert_eq!(metadata[0].datetime_string, "2020-03-04T170054-0800");
+    assert_eq!(metadata[0].suffix, ".tar.gz");
+    assert_eq!(metadata[0].utc_date, NaiveDate::from_ymd_opt(2020, 3, 5).unwrap());
+    assert_eq!(metadata[0].utc_time, NaiveTime::from_hms_opt(1, 0, 54).unwrap());
+
+    // Sort Codes are relative to other files with the same prefix and extensions.
+    let sort_codes: Vec<(String, usize, usize)> = metadata.iter()
+        .map(|each| (each.full_name.clone(), each.sort_code_newest.unwrap(), each.sort_code_oldest.unwrap()))
+        .collect();
+    assert_eq!(sort_codes, [
+        ("db_2020-03-04T170054-0800.tar.gz".to_string(), 2, 0),
+        ("db_2020-03-05T170054-0800.tar.gz".to_string(), 1, 1),
+        ("db_2020-03-06T170054-0800.tar.gz".to_string(), 0, 2),
+        ("web_2020-03-01T090000+0000.zip".to_string(), 0, 0)
+    ]);
+}
+
+#[test]
+fn sort_codes_with_sidecar_files() {
+    // A dump and its checksum are different backups; each is numbered among its own kind.
+    let paths: Vec<std::path::PathBuf> = ["db_2020-03-04T120000+0000.sql", "db_2020-03-05T120000+0000.sql",
+                                          "db_2020-03-05T120000+0000.sql.sha256"].iter().map(Into::into).collect();
+    let sort_codes: Vec<(usize, usize)> = filedate::metadata::metadata_from_paths(paths).iter()
+        .map(|each| (each.sort_code_newest.unwrap(), each.sort_code_oldest.unwrap()))
+        .collect();
+    assert_eq!(sort_codes, [(1, 0), (0, 1), (0, 0)]);
+}
+
+#[test]
+fn metadata_round_trip() {
+    let dated_path = DatedPathBuf::new_from_pathbuf("/backups/myfile_summary_2020-03-04T170054-0800.tar.gz".into()).unwrap();
+    let metadata: FileNameMeta = dated_path.metadata();
+    assert_eq!(metadata.parentdir, Path::new("/backups"));
+    assert_eq!(metadata.prefix, "myfile_summary");
+    assert_eq!(metadata.utc_datetime.to_rfc3339(), "2020-03-05T01:00:54+00:00");
+
+    let as_json: String = serde_json::to_string(&metadata).unwrap();
+    assert_eq!(serde_json::from_str::<FileNameMeta>(&as_json).unwrap(), metadata);
+
+    // 01:00 UTC on the 5th of March is still the 4th of March in Los Angeles.
+    let zoned: FileNameMeta = dated_path.metadata_in_zone(chrono_tz::America::Los_Angeles);
+    assert_eq!(zoned.zone.as_deref(), Some("America/Los_Angeles"));
+    assert_eq!(zoned.zone_date, NaiveDate::from_ymd_opt(2020, 3, 4));
+    assert_eq!(zoned.zone_time, NaiveTime::from_hms_opt(17, 0, 54));
+}
+
+#[test]
+fn rename_without_overwrite() {
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    let some_datetime = filedate::datetime_from_string("2020-03-04T170054-0800").ok();
+
+    fs::write(directory.join("db dump.tar.gz"), "first").unwrap();
+    let new_path = filedate::stamp::rename_with_stamp(&directory.join("db dump.tar.gz"), &some_datetime, &Default::default(), false).unwrap();
+    assert_eq!(new_path, directory.join("db dump_2020-03-04T170054-0800.tar.gz"));
+
+    // A second file with the same name, and same datetime, must not replace the first.
+    fs::write(directory.join("db dump.tar.gz"), "second").unwrap();
+    let result = filedate::stamp::rename_with_stamp(&directory.join("db dump.tar.gz"), &some_datetime, &Default::default(), false);
+    let contents = fs::read_to_string(&new_path).unwrap();
+
+    assert!(matches!(result, Err(Error::AlreadyExists(path)) if path == new_path));
+    assert_eq!(contents, "first");
+    assert_eq!(fs::read_to_string(directory.join("db dump.tar.gz")).unwrap(), "second");
+
+    // Renaming a file to its own name changes nothing; it is not a conflict.
+    assert!(filedate::stamp::check_rename(&new_path, &new_path, false).is_ok());
+    assert!(filedate::stamp::rename_path(&new_path, &new_path, false).is_ok());
+    let utc = filedate::stamp::StampOptions { zone: Some(filedate::stamp::StampZone::Utc), ..Default::default() };
+    let utc_path = filedate::stamp::convert_stamp_path(&new_path, &utc).unwrap();
+    filedate::stamp::rename_path(&new_path, &utc_path, false).unwrap();
+    assert_eq!(filedate::stamp::convert_stamp_path(&utc_path, &utc).unwrap(), utc_path);
+    assert!(filedate::stamp::rename_path(&utc_path, &utc_path, false).is_ok());
+    assert_eq!(fs::read_to_string(&utc_path).unwrap(), "first");
+    assert!(matches!(filedate::stamp::rename_path(&new_path, &new_path, false), Err(Error::NotFound(_))));
+    fs::rename(&utc_path, &new_path).unwrap();
+
+    // A dry run makes the same checks.  A dangling symbolic link is still a name that would be replaced.
+    let check = filedate::stamp::check_rename(&directory.join("db dump.tar.gz"), &new_path, false);
+    assert!(matches!(check, Err(Error::AlreadyExists(path)) if path == new_path));
+    #[cfg(unix)]
+    {
+        let dangling = directory.join("dangling.tar.gz");
+        std::os::unix::fs::symlink(directory.join("missing"), &dangling).unwrap();
+        let check = filedate::stamp::check_rename(&directory.join("db dump.tar.gz"), &dangling, false);
+        let result = filedate::stamp::rename_path(&directory.join("db dump.tar.gz"), &dangling, false);
+        assert!(matches!(check, Err(Error::AlreadyExists(_))));
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+    }
+}
+
+#[test]
+fn metadata_with_fractional_seconds() {
+    let dated_path = DatedPathBuf::new_from_pathbuf("dump_2024-01-02T030405.123Z.sql".into()).unwrap();
+    let metadata: FileNameMeta = dated_path.metadata();
+    assert_eq!(metadata.prefix, "dump");
+    assert_eq!(metadata.suffix, ".sql");
+    assert_eq!(metadata.datetime_string, "2024-01-02T030405.123Z");
+    assert_eq!(metadata.fractional_digits, 3);
+    assert_eq!(metadata.utc_time, NaiveTime::from_hms_milli_opt(3, 4, 5, 123).unwrap());
+}
+
+#[test]
+fn typed_errors() {
+    // Every failure is a variant of filedate::Error; nothing panics.
+    assert!(matches!(filedate::datetime_from_string("2020-13-04T170054-0800"), Err(Error::InvalidDateTime(_))));
+    assert!(matches!(filedate::split_stamped_name("notes.txt"), Err(Error::NoStamp(_))));
+    assert!(matches!(filedate::parse_filename_parts("."), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::parse_filename_parts(".."), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::parse_filename_bytes(b".."), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::stamp::stamp_path(Path::new("/"), &None), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::stamp::stamp_str(".", &None), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::stamp::stamp_str("..", &None), Err(Error::InvalidFileName(_))));
+    assert!(matches!(filedate::stamp::stamp_path(Path::new(".."), &None), Err(Error::InvalidFileName(_))));
+    assert!(matches!(DatedPathBuf::new_from_pathbuf("/backups/notes.txt".into()), Err(Error::NoStamp(_))));
+    assert!(matches!(filedate::build_metadata_from_path(Path::new("/no/such/filedate/path"), true), Err(Error::NotFound(_))));
+    assert!(matches!(filedate::metadata::files_in_directory(Path::new("/no/such/filedate/path")), Err(Error::NotFound(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_file_names() {
+    // A Latin-1 file name ('café' with 0xE9) is stamped, detected, and unstamped without losing any bytes.
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let some_datetime = filedate::datetime_from_string("2020-03-04T170054-0800").ok();
+    let original = Path::new("/backups").join(OsStr::from_bytes(b"caf\xe9.tar.gz"));
+
+    let stamped = filedate::stamp::stamp_path(&original, &some_datetime).unwrap();
+    assert_eq!(stamped.file_name().unwrap().as_bytes(), b"caf\xe9_2020-03-04T170054-0800.tar.gz");
+    assert!(filedate::is_path_file8601(&stamped));
+    assert_eq!(filedate::dates::path_datetime_indices(&stamped), Some((14, 36)));
+
+    let stamped_bytes = filedate::split_stamped_bytes(stamped.file_name().unwrap().as_bytes()).unwrap();
+    assert_eq!(stamped_bytes.prefix.as_ref(), b"caf\xe9");
+    assert_eq!(stamped_bytes.extension.as_deref(), Some(&b"tar.gz"[..]));
+
+    assert_eq!(filedate::stamp::unstamp_path(&stamped).unwrap(), original);
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_directories() {
+    // Neither the directory nor the file name must be UTF-8.  Metadata for a Latin-1 name is lossy; grouping is not.
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use filedate::retention::{RetentionPlan, RetentionPolicy};
+
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: std::path::PathBuf = temporary.path().join(OsStr::from_bytes(b"caf\xe9"));
+    fs::create_dir(&directory).unwrap();
+    for name in [&b"db_2020-03-04T170054-0800.sql"[..], b"db_2020-03-05T170054-0800.sql",
+                 b"caf\xe9_2020-03-05T170054-0800.sql", b"caf\xe8_2020-03-06T170054-0800.sql", b"caf\xe9.txt"] {
+        fs::write(directory.join(OsStr::from_bytes(name)), "").unwrap();
+    }
+
+    let single = filedate::metadata_from_name_in_zone(&directory.join("db_2020-03-04T170054-0800.sql"), None).unwrap();
+    assert_eq!(single.prefix, "db");
+    assert_eq!(single.parentdir, directory);
+
+    let walk = filedate::walk::walk_directory(&directory, &filedate::walk::WalkOptions::default()).unwrap();
+    let metadata = filedate::metadata::metadata_from_paths(walk.files.clone());
+    assert_eq!(metadata.len(), 4);
+    let latin1 = metadata.iter().find(|each| each.path.ends_with(OsStr::from_bytes(b"caf\xe9_2020-03-05T170054-0800.sql"))).unwrap();
+    assert_eq!(latin1.full_name, "caf\u{FFFD}_2020-03-05T170054-0800.sql");
+    assert_eq!(latin1.prefix, "caf\u{FFFD}");
+    assert_eq!(latin1.suffix, ".sql");
+    assert_eq!((latin1.sort_code_newest, latin1.sort_code_oldest), (Some(0), Some(0)));
+
+    // 'caf\xe8' and 'caf\xe9' have the same lossy prefix, but are different backups.
+    let policy = RetentionPolicy { keep_last: 1, ..Default::default() };
+    let plan: RetentionPlan = filedate::retention::apply_policy(&policy, &walk.files);
+    assert_eq!(plan.delete, [directory.join("db_2020-03-04T170054-0800.sql")]);
+}
+
+#[test]
+fn prune_overlapping_directories() {
+    // A file found by walking 2 overlapping directories is still 1 backup; it is either kept or deleted, never both.
+    use filedate::retention::{RetentionPlan, RetentionPolicy};
+    use filedate::walk::WalkOptions;
+
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    fs::create_dir(directory.join("db")).unwrap();
+    for name in ["db/db_2020-03-04T170054-0800.sql", "db/db_2020-03-05T170054-0800.sql"] {
+        fs::write(directory.join(name), "").unwrap();
+    }
+
+    let mut paths = filedate::walk::walk_directory(directory, &WalkOptions::default()).unwrap().files;
+    paths.extend(filedate::walk::walk_directory(&directory.join("db"), &WalkOptions::default()).unwrap().files);
+    assert_eq!(paths.len(), 4);
+
+    let policy = RetentionPolicy { keep_last: 1, ..Default::default() };
+    let plan: RetentionPlan = filedate::retention::apply_policy(&policy, &paths);
+    assert_eq!(plan.keep.len(), 1);
+    assert_eq!(plan.keep[0].path, directory.join("db/db_2020-03-05T170054-0800.sql"));
+    assert_eq!(plan.delete, [directory.join("db/db_2020-03-04T170054-0800.sql")]);
+}
+
+#[test]
+fn recursive_walk() {
+    // Subdirectories are read up to --max-depth; --include and --exclude filter by file name, or by relative path.
+    use filedate::walk::{Walk, WalkOptions};
+
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    fs::create_dir_all(directory.join("db/old")).unwrap();
+    for name in ["top_2020-03-01T000000+0000.zip", "db/db_2020-03-04T170054-0800.tar.gz",
+                 "db/db_2020-03-04T170054-0800.sql", "db/old/db_2019-03-04T170054-0800.tar.gz"] {
+        fs::write(directory.join(name), "").unwrap();
     }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(directory, directory.join("db/loop")).unwrap();
+
+    let names = |walk: &Walk| -> Vec<String> {
+        walk.files.iter()
+            .map(|path| path.strip_prefix(directory).unwrap().to_str().unwrap().to_owned())
+            .collect()
+    };
+
+    let walk = filedate::walk::walk_directory(directory, &WalkOptions::single_level()).unwrap();
+    assert_eq!(names(&walk), ["top_2020-03-01T000000+0000.zip"]);
+
+    let options = WalkOptions { max_depth: Some(2), ..Default::default() };
+    let walk = filedate::walk::walk_directory(directory, &options).unwrap();
+    assert_eq!(names(&walk), ["db/db_2020-03-04T170054-0800.sql", "db/db_2020-03-04T170054-0800.tar.gz",
+                              "top_2020-03-01T000000+0000.zip"]);
+
+    let options = WalkOptions {
+        include: vec![glob::Pattern::new("*.tar.gz").unwrap()],
+        exclude: vec![glob::Pattern::new("db/old").unwrap()],
+        ..Default::default()
+    };
+    let walk = filedate::walk::walk_directory(directory, &options).unwrap();
+    assert_eq!(names(&walk), ["db/db_2020-03-04T170054-0800.tar.gz"]);
+    assert!(walk.warnings.is_empty());
+
+    // Following the symbolic link back to the top would never end.  So it becomes a warning instead.
+    #[cfg(unix)]
+    {
+        let options = WalkOptions { follow_symlinks: true, ..Default::default() };
+        let walk = filedate::walk::walk_directory(directory, &options).unwrap();
+        assert_eq!(walk.files.len(), 4);
+        assert!(matches!(walk.warnings.as_slice(), [Error::SymlinkLoop(path)] if path.ends_with("db/loop")));
+
+        // The library's metadata entry points walk the same way; and return the same warnings.
+        let (json, warnings) = filedate::build_metadata_from_walk(directory, true, None, &options).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<FileNameMeta>>(&json).unwrap().len(), 4);
+        assert!(matches!(warnings.as_slice(), [Error::SymlinkLoop(_)]));
+    }
+    let single_level: Vec<FileNameMeta> = serde_json::from_str(&filedate::build_metadata_from_path(directory, true).unwrap()).unwrap();
+    assert_eq!(single_level.len(), 1);
+
+    let missing = filedate::walk::walk_directory(&directory.join("missing"), &WalkOptions::default());
+    assert!(matches!(missing, Err(Error::NotFound(_))));
+}
+
+#[test]
+fn parallel_walk_is_deterministic() {
+    // The number of threads never changes the result, nor its order.
+    use filedate::walk::{Walk, WalkOptions};
+
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    for host in 0..8 {
+        let host_directory = directory.join(format!("host{}", host)).join("daily");
+        fs::create_dir_all(&host_directory).unwrap();
+        for day in 1..=20 {
+            fs::write(host_directory.join(format!("db_2020-03-{:02}T170054-0800.sql", day)), "").unwrap();
+        }
+    }
+
+    let walk_with = |threads: usize| -> (Walk, Vec<FileNameMeta>) {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            let walk: Walk = filedate::walk::walk_directory(directory, &WalkOptions::default()).unwrap();
+            let metadata = filedate::metadata::metadata_from_paths(walk.files.clone());
+            (walk, metadata)
+        })
+    };
+    let (serial, serial_metadata) = walk_with(1);
+    let (parallel, parallel_metadata) = walk_with(8);
+
+    assert_eq!(serial.files.len(), 160);
+    assert!(serial.files.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(serial.files, parallel.files);
+    assert_eq!(serial_metadata, parallel_metadata);
+
+    // Sort Codes are per directory, prefix and extensions; so every host's newest file is 0.
+    assert_eq!(parallel_metadata.iter().filter(|each| each.sort_code_newest == Some(0)).count(), 8);
+}
+
+#[test]
+fn touch_from_stamp() {
+    // The modification time becomes the instant in the file name; a second call has nothing left to change.
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    let path = directory.join("db_2020-03-04T170054.250-0800.sql");
+    fs::write(&path, "").unwrap();
+
+    assert!(filedate::stamp::touch_from_stamp(&path, false, true).unwrap().changed);
+    assert!(fs::metadata(&path).unwrap().modified().unwrap() > std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_600_000_000));
+
+    let touched = filedate::stamp::touch_from_stamp(&path, true, false).unwrap();
+    assert_eq!((touched.path.as_path(), touched.changed), (path.as_path(), true));
+    let metadata = fs::metadata(&path).unwrap();
+    let expected: DateTime<Utc> = Utc.with_ymd_and_hms(2020, 3, 5, 1, 0, 54).unwrap() + chrono::Duration::milliseconds(250);
+    assert_eq!(DateTime::<Utc>::from(metadata.modified().unwrap()), expected);
+    assert_eq!(DateTime::<Utc>::from(metadata.accessed().unwrap()), expected);
+    assert_eq!(touched.stamp.with_timezone(&Utc), expected);
+    assert!(!filedate::stamp::touch_from_stamp(&path, true, false).unwrap().changed);
+
+    let unstamped = directory.join("notes.txt");
+    fs::write(&unstamped, "").unwrap();
+    let result = filedate::stamp::touch_from_stamp(&unstamped, false, false);
+    assert!(matches!(result, Err(Error::NoStamp(_))));
+}
+
+#[test]
+fn verify_drift() {
+    // A file whose modification time is far from its stamp has drifted; touching it fixes that.
+    use filedate::verify::Verdict;
+
+    let temporary = tempfile::tempdir().unwrap();
+    let directory: &Path = temporary.path();
+    let path = directory.join("db_2020-03-04T170054-0800.sql");
+    fs::write(&path, "").unwrap();
+    let five_minutes = chrono::Duration::minutes(5);
+
+    let drifted = filedate::verify::verify_path(&path, five_minutes).unwrap();
+    assert_eq!(drifted.verdict, Verdict::Drift);
+    assert!(drifted.delta_seconds > 0.0);
+    assert_eq!(drifted.stamp, filedate::datetime_from_string("2020-03-04T170054-0800").unwrap());
+
+    filedate::stamp::touch_from_stamp(&path, false, false).unwrap();
+    let results = filedate::verify::verify_paths(&[path.clone(), directory.join("notes.txt")], five_minutes);
+
+    let verified = results[0].as_ref().unwrap();
+    assert_eq!((verified.verdict, verified.delta_seconds), (Verdict::Ok, 0.0));
+    assert_eq!(verified.delta_string(), "+0.000s");
+    assert!(matches!(results[1], Err(Error::NoStamp(_))));
 }